@@ -47,10 +47,15 @@ fn bubble_sort(b: &mut Bencher) {
     for _ in 1..1000 {
         s += C;
     }
-    println!("Bytes {}", s.bytes().len());
-    b.iter(|| {
-        test::black_box({
-            Lexer::lex(&s[..]).unwrap();
-        })
-    });
-}
\ No newline at end of file
+    println!("Bytes {}", s.len());
+    b.iter(|| test::black_box(Lexer::lex(&s[..]).unwrap()));
+}
+
+#[bench]
+fn bubble_sort_streaming(b: &mut Bencher) {
+    let mut s = String::new();
+    for _ in 1..1000 {
+        s += C;
+    }
+    b.iter(|| test::black_box(Lexer::new(&s[..]).count()));
+}
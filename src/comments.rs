@@ -0,0 +1,165 @@
+//! Extraction of comments for documentation tools.
+//!
+//! Comments are classified as `//` or `/* */` comments and as
+//! documentation comments in Javadoc style, `/**` and `///`, or in Qt
+//! style, `/*!` and `//!`, like Doxygen does. Each documentation comment
+//! is associated with the next token after it, which starts what the
+//! comment documents.
+
+use crate::{
+    error::Error,
+    options::LexerOptions,
+    source_map,
+    span::Span,
+    token::Token,
+    trivia::{lex_lossless, TokenOrTrivia, Trivia},
+};
+
+/// Comment delimiters
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum CommentKind {
+    /// `//` comment
+    Line,
+    /// `/* */` comment
+    Block,
+}
+
+/// Style of documentation comment
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum DocStyle {
+    /// `/**` or `///`
+    Javadoc,
+    /// `/*!` or `//!`
+    Qt,
+}
+
+/// Comment in the source
+#[derive(PartialEq, Debug, Clone)]
+pub struct Comment {
+    /// Delimiters of the comment
+    pub kind: CommentKind,
+    /// Style of documentation comment, `None` for other comments
+    pub doc: Option<DocStyle>,
+    /// Exact text of the comment including delimiters
+    pub text: String,
+    /// Location of the comment
+    pub span: Span,
+    /// First token after documentation comment other than a line
+    /// terminator, `None` for other comments and at the end of input
+    pub documents: Option<(Token, Span)>,
+}
+
+// Style of documentation comment, `text` has lines spliced. Comments
+// like `////` or `/***` are decorations rather than documentation.
+#[inline]
+fn doc_style(kind: CommentKind, text: &[u8]) -> Option<DocStyle> {
+    match (kind, text.get(2), text.get(3)) {
+        (_, Some(b'!'), _) => Some(DocStyle::Qt),
+        (CommentKind::Line, Some(b'/'), next) if next != Some(&b'/') => Some(DocStyle::Javadoc),
+        (CommentKind::Block, Some(b'*'), next) if !matches!(next, Some(b'*') | Some(b'/')) => {
+            Some(DocStyle::Javadoc)
+        }
+        _ => None,
+    }
+}
+
+/// Extract all comments from input in source order
+pub fn comments(input: &str, options: LexerOptions) -> Result<Vec<Comment>, Error> {
+    let tokens = lex_lossless(input, options)?;
+    let mut comments = Vec::new();
+    let mut next = None;
+    // going backwards the next token is known when a comment is reached
+    for token in tokens.into_iter().rev() {
+        let kind = match token.kind {
+            TokenOrTrivia::Token(Token::LineTerminator) | TokenOrTrivia::Token(Token::EOF) => {
+                continue
+            }
+            TokenOrTrivia::Token(t) => {
+                next = Some((t, token.span));
+                continue;
+            }
+            TokenOrTrivia::Trivia(Trivia::Whitespace) => continue,
+            TokenOrTrivia::Trivia(Trivia::LineComment) => CommentKind::Line,
+            TokenOrTrivia::Trivia(Trivia::BlockComment) => CommentKind::Block,
+        };
+        let spliced = source_map::translate(token.text.as_bytes(), options.trigraphs).0;
+        let doc = doc_style(kind, &spliced);
+        comments.push(Comment {
+            kind,
+            doc,
+            text: token.text,
+            span: token.span,
+            documents: doc.and_then(|_| next.clone()),
+        });
+    }
+    comments.reverse();
+    Ok(comments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use internship::IStr;
+
+    fn comments(input: &str) -> Vec<(CommentKind, Option<DocStyle>, String, Option<Token>)> {
+        super::comments(input, LexerOptions::default())
+            .unwrap()
+            .into_iter()
+            .map(|c| (c.kind, c.doc, c.text, c.documents.map(|(t, _)| t)))
+            .collect()
+    }
+
+    #[test]
+    fn styles() {
+        let f = || Some(Token::Identifier(IStr::new("f")));
+        assert_eq!(
+            comments("/** a */ /*! b */\n/// c\n//! d\nf /* e */ // g\n//// h\n/*** i */ /**/"),
+            vec![
+                (
+                    CommentKind::Block,
+                    Some(DocStyle::Javadoc),
+                    "/** a */".to_string(),
+                    f()
+                ),
+                (
+                    CommentKind::Block,
+                    Some(DocStyle::Qt),
+                    "/*! b */".to_string(),
+                    f()
+                ),
+                (
+                    CommentKind::Line,
+                    Some(DocStyle::Javadoc),
+                    "/// c".to_string(),
+                    f()
+                ),
+                (
+                    CommentKind::Line,
+                    Some(DocStyle::Qt),
+                    "//! d".to_string(),
+                    f()
+                ),
+                (CommentKind::Block, None, "/* e */".to_string(), None),
+                (CommentKind::Line, None, "// g".to_string(), None),
+                (CommentKind::Line, None, "//// h".to_string(), None),
+                (CommentKind::Block, None, "/*** i */".to_string(), None),
+                (CommentKind::Block, None, "/**/".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn documented_tokens() {
+        let input = "/**\n * Adds.\n */\nint add(int a, int b);\n/\\\n// spliced\nstruct s;\n///";
+        let comments = super::comments(input, LexerOptions::default()).unwrap();
+        assert_eq!(comments.len(), 3);
+        let (token, span) = comments[0].documents.clone().unwrap();
+        assert_eq!(token, Token::INT);
+        assert_eq!(span.start.line, 4);
+        assert_eq!(comments[1].text, "/\\\n// spliced");
+        assert_eq!(comments[1].doc, Some(DocStyle::Javadoc));
+        assert_eq!(comments[1].documents.clone().unwrap().0, Token::STRUCT);
+        assert_eq!(comments[2].doc, Some(DocStyle::Javadoc));
+        assert_eq!(comments[2].documents, None);
+    }
+}
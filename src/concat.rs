@@ -0,0 +1,222 @@
+//! Concatenation of adjacent string literals, translation phase 6.
+//!
+//! Pieces are concatenated as written, so `"\\x12" "3"` stays two
+//! characters. If any piece has an encoding prefix the result has it
+//! too and plain pieces are decoded again in that encoding, pieces with
+//! different prefixes are an error.
+
+use crate::{
+    error::{Error, ErrorKind},
+    options::LexerOptions,
+    span::Span,
+    string::reencode,
+    token::{CodeUnits, Encoding, SpannedToken, StringLiteral, Token},
+};
+
+/// Token after concatenation of string literals
+#[derive(PartialEq, Debug, Clone)]
+pub struct ConcatenatedToken {
+    /// Token, adjacent string literals are merged into one
+    pub token: Token,
+    /// Location of the whole token, for merged string literals from the
+    /// start of the first piece to the end of the last one
+    pub span: Span,
+    /// Locations of the string literals the token was merged from, empty
+    /// for other tokens
+    pub pieces: Vec<Span>,
+}
+
+impl ConcatenatedToken {
+    /// Create new token
+    #[inline]
+    pub fn new(token: Token, span: Span, pieces: Vec<Span>) -> Self {
+        Self {
+            token,
+            span,
+            pieces,
+        }
+    }
+}
+
+#[inline]
+fn append(units: &mut CodeUnits, other: CodeUnits) {
+    match (units, other) {
+        (CodeUnits::U8(units), CodeUnits::U8(other)) => units.extend(other),
+        (CodeUnits::U16(units), CodeUnits::U16(other)) => units.extend(other),
+        (CodeUnits::U32(units), CodeUnits::U32(other)) => units.extend(other),
+        // all pieces are encoded with the same encoding
+        _ => {}
+    }
+}
+
+// Merges run of string literals, raw spelling of the result is the
+// spellings of the pieces put next to each other, which lexes the same
+#[inline]
+fn merge(
+    pieces: Vec<(StringLiteral, Span)>,
+    options: &LexerOptions,
+) -> Result<ConcatenatedToken, Error> {
+    let mut encoding = Encoding::Plain;
+    for (literal, span) in &pieces {
+        match (encoding, literal.encoding) {
+            (_, Encoding::Plain) => {}
+            (Encoding::Plain, prefixed) => encoding = prefixed,
+            (first, second) if first == second => {}
+            (first, second) => {
+                let kind = ErrorKind::IncompatibleEncodings(first, second);
+                return Err(Error::new(kind, span.start));
+            }
+        }
+    }
+
+    let span = Span::new(pieces[0].1.start, pieces[pieces.len() - 1].1.end);
+    let mut raw = String::new();
+    let mut value: Option<CodeUnits> = None;
+    let mut spans = Vec::with_capacity(pieces.len());
+    for (literal, span) in pieces {
+        let units = if literal.encoding == encoding {
+            literal.value.clone()
+        } else {
+            reencode(&literal, encoding, options).map_err(|e| Error::new(e.kind, span.start))?
+        };
+        match value.as_mut() {
+            Some(value) => append(value, units),
+            None => value = Some(units),
+        }
+        raw.push_str(&literal.raw);
+        spans.push(span);
+    }
+    let value = value.unwrap_or(CodeUnits::U8(Vec::new()));
+    let token = Token::StringLiteral(StringLiteral::new(raw, encoding, value));
+    Ok(ConcatenatedToken::new(token, span, spans))
+}
+
+/// Merge adjacent string literals in lexed tokens, only line terminators
+/// may separate them and those are dropped
+pub fn concatenate(
+    tokens: Vec<SpannedToken>,
+    options: &LexerOptions,
+) -> Result<Vec<ConcatenatedToken>, Error> {
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut pieces = Vec::new();
+    let mut separators = Vec::new();
+    for SpannedToken { token, span, .. } in tokens {
+        match token {
+            Token::StringLiteral(literal) => {
+                separators.clear();
+                pieces.push((literal, span));
+            }
+            Token::LineTerminator if !pieces.is_empty() => {
+                separators.push((token, span));
+            }
+            token => {
+                if !pieces.is_empty() {
+                    result.push(merge(pieces.split_off(0), options)?);
+                }
+                result.extend(
+                    separators
+                        .drain(..)
+                        .map(|(token, span)| ConcatenatedToken::new(token, span, Vec::new())),
+                );
+                result.push(ConcatenatedToken::new(token, span, Vec::new()));
+            }
+        }
+    }
+    if !pieces.is_empty() {
+        result.push(merge(pieces, options)?);
+    }
+    result.extend(
+        separators
+            .into_iter()
+            .map(|(token, span)| ConcatenatedToken::new(token, span, Vec::new())),
+    );
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{span::Position, Lexer};
+    use ::internship::IStr;
+
+    fn concat(input: &str) -> Result<Vec<ConcatenatedToken>, Error> {
+        concatenate(Lexer::lex_spanned(input)?, &LexerOptions::default())
+    }
+
+    fn string(raw: &str, encoding: Encoding, value: CodeUnits) -> Token {
+        Token::StringLiteral(StringLiteral::new(raw.to_string(), encoding, value))
+    }
+
+    #[test]
+    fn plain() {
+        let tokens = concat("s = \"ab\" \"c\"\n  \"\\x12\" \"3\";").unwrap();
+        let tokens: Vec<_> = tokens.into_iter().map(|t| t.token).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier(IStr::new("s")),
+                Token::Assign,
+                string(
+                    "\"ab\"\"c\"\"\\x12\"\"3\"",
+                    Encoding::Plain,
+                    CodeUnits::U8(b"abc\x123".to_vec())
+                ),
+                Token::Semicolon,
+                Token::EOF,
+            ]
+        );
+    }
+
+    #[test]
+    fn pieces() {
+        let tokens = concat("\"a\"\n\"b\" x \"c\"").unwrap();
+        assert_eq!(tokens[0].pieces.len(), 2);
+        assert_eq!(tokens[0].pieces[1].start, Position::new(4, 2, 1));
+        assert_eq!(tokens[0].span.start, Position::new(0, 1, 1));
+        assert_eq!(tokens[0].span.end, Position::new(7, 2, 4));
+        assert_eq!(tokens[1].token, Token::Identifier(IStr::new("x")));
+        assert!(tokens[1].pieces.is_empty());
+        assert_eq!(tokens[2].pieces.len(), 1);
+    }
+
+    #[test]
+    fn line_terminators_kept() {
+        let tokens = concat("\"a\"\nx").unwrap();
+        let tokens: Vec<_> = tokens.into_iter().map(|t| t.token).collect();
+        assert_eq!(tokens[1], Token::LineTerminator);
+        assert_eq!(tokens[2], Token::Identifier(IStr::new("x")));
+    }
+
+    #[test]
+    fn promotion() {
+        let tokens = concat("\"a\\xff\" L\"é\" \"b\"").unwrap();
+        assert_eq!(
+            tokens[0].token,
+            string(
+                "\"a\\xff\"L\"é\"\"b\"",
+                Encoding::Wide,
+                CodeUnits::U32(vec![0x61, 0xFF, 0xE9, 0x62])
+            )
+        );
+        let tokens = concat("u8\"a\" \"é\" u8\"b\"").unwrap();
+        assert_eq!(
+            tokens[0].token,
+            string(
+                "u8\"a\"\"é\"u8\"b\"",
+                Encoding::Utf8,
+                CodeUnits::U8("aéb".as_bytes().to_vec())
+            )
+        );
+    }
+
+    #[test]
+    fn incompatible() {
+        assert_eq!(
+            concat("\"a\" u\"b\"\n L\"c\"").unwrap_err(),
+            Error::new(
+                ErrorKind::IncompatibleEncodings(Encoding::Utf16, Encoding::Wide),
+                Position::new(10, 2, 2)
+            )
+        );
+    }
+}
@@ -0,0 +1,521 @@
+//! Lexing with conditional compilation but without preprocessing.
+//!
+//! `#if`, `#ifdef`, `#ifndef`, `#elif`, `#elifdef`, `#elifndef`, `#else`
+//! and `#endif` are evaluated against a set of macros given by the
+//! caller. Macros are not expanded, so identifiers in `#if` are `0` and
+//! `#define` and `#undef` don't change the set. Tokens of active groups
+//! are returned, including other directives, and inactive groups are
+//! reported as skipped spans. Conditional directives are dropped.
+
+use crate::{
+    error::{Error, ErrorKind},
+    expr,
+    options::LexerOptions,
+    span::{Position, Span},
+    state_machine::Tokenizer,
+    token::{SpannedToken, Token},
+};
+use std::collections::HashSet;
+
+/// Tokens of active groups of conditional compilation
+#[derive(PartialEq, Debug, Clone)]
+pub struct ActiveTokens {
+    /// Tokens outside of conditional directives and skipped groups,
+    /// ends with [`Token::EOF`]
+    pub tokens: Vec<SpannedToken>,
+    /// Locations of skipped groups in source order, from the start of
+    /// the line after the directive that skips the group to the `#` of
+    /// the directive that ends it
+    pub skipped: Vec<Span>,
+}
+
+// Token looked at by conditional directives and their conditions
+pub(crate) trait DirectiveToken {
+    fn token(&self) -> &Token;
+    fn span(&self) -> Span;
+    fn line_start(&self) -> bool;
+    // name of identifier or keyword, keywords are ordinary identifiers
+    // to the preprocessor and named as written
+    fn name(&self) -> Option<&str>;
+}
+
+// Token lexed by `lex_active` with the keyword it is spelled as
+struct ActiveToken {
+    token: SpannedToken,
+    keyword: Option<&'static str>,
+}
+
+impl DirectiveToken for ActiveToken {
+    #[inline]
+    fn token(&self) -> &Token {
+        &self.token.token
+    }
+
+    #[inline]
+    fn span(&self) -> Span {
+        self.token.span
+    }
+
+    #[inline]
+    fn line_start(&self) -> bool {
+        self.token.line_start
+    }
+
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        match &self.token.token {
+            Token::Identifier(name) => Some(name),
+            _ => self.keyword,
+        }
+    }
+}
+
+// Conditional directive being processed
+#[derive(Debug, Clone, Copy)]
+struct Conditional {
+    // `#else` was seen
+    in_else: bool,
+    // some group of the conditional is included
+    taken: bool,
+    position: Position,
+}
+
+// Conditional directives being processed, innermost last
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Conditionals(Vec<Conditional>);
+
+impl Conditionals {
+    // Process conditional directive at `at`, `condition` is evaluated
+    // unless a group of the conditional was taken already. Returns
+    // whether the group after the directive is taken.
+    pub(crate) fn directive<F>(
+        &mut self,
+        directive: &str,
+        at: Position,
+        condition: F,
+    ) -> Result<bool, Error>
+    where
+        F: FnOnce() -> Result<bool, Error>,
+    {
+        match directive {
+            "if" | "ifdef" | "ifndef" => {
+                let taken = condition()?;
+                self.0.push(Conditional {
+                    in_else: false,
+                    taken,
+                    position: at,
+                });
+                Ok(taken)
+            }
+            "elif" | "elifdef" | "elifndef" | "else" => {
+                let conditional = match self.0.last_mut() {
+                    Some(conditional) if conditional.in_else => {
+                        let kind = ErrorKind::ConditionalAfterElse(directive.to_string());
+                        return Err(Error::new(kind, at));
+                    }
+                    Some(conditional) => conditional,
+                    None => {
+                        let kind = ErrorKind::UnmatchedConditional(directive.to_string());
+                        return Err(Error::new(kind, at));
+                    }
+                };
+                conditional.in_else = directive == "else";
+                // condition is not evaluated once a group is taken
+                let take = !conditional.taken && (directive == "else" || condition()?);
+                conditional.taken |= take;
+                Ok(take)
+            }
+            _ => match self.0.pop() {
+                Some(_) => Ok(true),
+                None => {
+                    let kind = ErrorKind::UnmatchedConditional(directive.to_string());
+                    Err(Error::new(kind, at))
+                }
+            },
+        }
+    }
+
+    // Whether condition of conditional directive is evaluated, lines of
+    // the others don't have to be lexed correctly
+    #[inline]
+    pub(crate) fn evaluates(&self, directive: &str) -> bool {
+        match directive {
+            "if" | "ifdef" | "ifndef" => true,
+            "elif" | "elifdef" | "elifndef" => self
+                .0
+                .last()
+                .is_some_and(|conditional| !conditional.in_else && !conditional.taken),
+            _ => false,
+        }
+    }
+
+    // Fail if some conditional is not terminated at the end of input
+    #[inline]
+    pub(crate) fn end(&self) -> Result<(), Error> {
+        match self.0.last() {
+            Some(conditional) => Err(Error::new(
+                ErrorKind::UnterminatedConditional,
+                conditional.position,
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+// Skip a group up to `#elif`, `#else` or `#endif` of the current
+// conditional and return `#` and name of that directive, `None` at the
+// end of input. Only `#` starting a line is looked at, so the rest of
+// skipped lines doesn't matter.
+pub(crate) fn skip_group<T, F>(mut next: F) -> Option<(T, T)>
+where
+    T: DirectiveToken,
+    F: FnMut() -> Option<T>,
+{
+    let mut depth = 0;
+    while let Some(hash) = next() {
+        if !hash.line_start() || *hash.token() != Token::Hash {
+            continue;
+        }
+        let name = match next() {
+            Some(name) if *name.token() == Token::LineTerminator => continue,
+            Some(name) => name,
+            None => return None,
+        };
+        match name.name() {
+            Some("if") | Some("ifdef") | Some("ifndef") => depth += 1,
+            Some("endif") if depth > 0 => depth -= 1,
+            Some("elif") | Some("elifdef") | Some("elifndef") | Some("else")
+            | Some("endif")
+                if depth == 0 =>
+            {
+                return Some((hash, name))
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Evaluate condition of `#if` or `#elif` family directive, `at` is the
+// end of the directive name and `end` the end of the condition. Macros
+// are looked up by `defined`.
+pub(crate) fn condition<T: DirectiveToken, F: Fn(&str) -> bool>(
+    directive: &str,
+    tokens: &[T],
+    at: Position,
+    end: Position,
+    defined: F,
+) -> Result<bool, Error> {
+    let expected = match directive {
+        "ifdef" | "elifdef" => true,
+        "ifndef" | "elifndef" => false,
+        _ => return expr::evaluate(tokens, end, defined),
+    };
+    let name = tokens
+        .first()
+        .and_then(T::name)
+        .ok_or_else(|| Error::new(ErrorKind::MissingMacroName, at))?;
+    Ok(defined(name) == expected)
+}
+
+struct Active<'a, 's> {
+    tokenizer: Tokenizer<'a>,
+    defined: &'s HashSet<String>,
+    conditionals: Conditionals,
+    tokens: Vec<SpannedToken>,
+    skipped: Vec<Span>,
+}
+
+impl<'a, 's> Active<'a, 's> {
+    #[inline]
+    fn named(&self, token: SpannedToken) -> ActiveToken {
+        let keyword = match token.token {
+            Token::Identifier(_) => None,
+            _ => self.tokenizer.keyword_spelling(token.span),
+        };
+        ActiveToken { token, keyword }
+    }
+
+    // Rest of the directive line
+    fn line(&mut self) -> Result<Vec<ActiveToken>, Error> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.tokenizer.next_token()? {
+            if token.token == Token::LineTerminator {
+                break;
+            }
+            tokens.push(self.named(token));
+        }
+        Ok(tokens)
+    }
+
+    // Rest of the line whose tokens don't matter, it may not be lexed
+    // correctly
+    fn skip_line(&mut self) {
+        loop {
+            match self.tokenizer.next_token() {
+                Ok(Some(token)) if token.token != Token::LineTerminator => {}
+                Ok(_) => return,
+                Err(_) => self.tokenizer.skip_line(),
+            }
+        }
+    }
+
+    // Next token of a skipped group, lines that can't be lexed are
+    // skipped too
+    fn next_skipped(&mut self) -> Option<ActiveToken> {
+        loop {
+            match self.tokenizer.next_token() {
+                Ok(token) => return token.map(|token| self.named(token)),
+                Err(_) => self.tokenizer.skip_line(),
+            }
+        }
+    }
+
+    // Skip a group and record its span, returns the directive ending it
+    fn skip_group(&mut self) -> Option<(ActiveToken, ActiveToken)> {
+        let start = self.tokenizer.position();
+        self.tokenizer.set_skipping(true);
+        let end = skip_group(|| self.next_skipped());
+        self.tokenizer.set_skipping(false);
+        let stop = end
+            .as_ref()
+            .map_or_else(|| self.tokenizer.position(), |(hash, _)| hash.span().start);
+        if stop.offset > start.offset {
+            self.skipped.push(Span::new(start, stop));
+        }
+        end
+    }
+
+    // Process conditional directive, returns the directive ending the
+    // group skipped by it if any
+    fn directive(
+        &mut self,
+        hash: &ActiveToken,
+        name: &ActiveToken,
+        directive: &str,
+    ) -> Result<Option<(ActiveToken, ActiveToken)>, Error> {
+        let tokens = if self.conditionals.evaluates(directive) {
+            self.line()?
+        } else {
+            self.skip_line();
+            Vec::new()
+        };
+        let at = name.span().end;
+        let end = tokens.last().map_or(at, |t| t.span().end);
+        let defined = self.defined;
+        let take = self.conditionals.directive(directive, hash.span().start, || {
+            condition(directive, &tokens, at, end, |name| defined.contains(name))
+        })?;
+        Ok(if take { None } else { self.skip_group() })
+    }
+}
+
+#[inline]
+pub(crate) fn is_conditional(directive: &str) -> bool {
+    matches!(
+        directive,
+        "if" | "ifdef" | "ifndef" | "elif" | "elifdef" | "elifndef" | "else" | "endif"
+    )
+}
+
+/// Lex input keeping only groups of conditional directives that are
+/// active when exactly the macros in `defined` are defined
+pub fn lex_active(
+    input: &str,
+    defined: &HashSet<String>,
+    options: LexerOptions,
+) -> Result<ActiveTokens, Error> {
+    let mut active = Active {
+        tokenizer: Tokenizer::with_options(input, options),
+        defined,
+        conditionals: Conditionals::default(),
+        tokens: Vec::new(),
+        skipped: Vec::new(),
+    };
+    while let Some(token) = active.tokenizer.next_token()? {
+        if !token.line_start || token.token != Token::Hash {
+            active.tokens.push(token);
+            continue;
+        }
+        let mut directive = match active.tokenizer.next_token()? {
+            Some(name) => Some((active.named(token), active.named(name))),
+            None => {
+                active.tokens.push(token);
+                break;
+            }
+        };
+        // a directive ending a skipped group is processed right away
+        while let Some((hash, name)) = directive.take() {
+            match name.name().filter(|name| is_conditional(name)) {
+                Some(kind) => {
+                    let kind = kind.to_string();
+                    directive = active.directive(&hash, &name, &kind)?;
+                }
+                None => {
+                    active.tokens.push(hash.token);
+                    active.tokens.push(name.token);
+                }
+            }
+        }
+    }
+    active.conditionals.end()?;
+    let mut tokens = active.tokens;
+    tokens.push(active.tokenizer.eof());
+    Ok(ActiveTokens {
+        tokens,
+        skipped: active.skipped,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{options::Standard, Lexer};
+    use internship::IStr;
+
+    fn active(input: &str, defined: &[&str]) -> Result<ActiveTokens, Error> {
+        let defined = defined.iter().map(|name| name.to_string()).collect();
+        lex_active(input, &defined, LexerOptions::default())
+    }
+
+    // Spelling of active tokens without line terminators
+    fn spelling(input: &str, defined: &[&str]) -> Vec<String> {
+        let active = active(input, defined).unwrap();
+        active
+            .tokens
+            .iter()
+            .filter(|t| !matches!(t.token, Token::LineTerminator | Token::EOF))
+            .map(|t| input[t.span.start.offset..t.span.end.offset].to_string())
+            .collect()
+    }
+
+    #[test]
+    fn groups() {
+        let input = r#"#ifdef A
+a
+#elif B > 0 || defined(B)
+b
+#else
+c
+#endif
+#ifndef A
+#if 0
+# if 1
+#else
+#endif
+'unterminated
+#elifndef C
+d
+#elifdef B
+e
+#endif
+#endif
+#define F
+f
+"#;
+        assert_eq!(spelling(input, &["A"]), vec!["a", "#", "define", "F", "f"]);
+        assert_eq!(
+            spelling(input, &["B"]),
+            vec!["b", "d", "#", "define", "F", "f"]
+        );
+        assert_eq!(
+            spelling(input, &["B", "C"]),
+            vec!["b", "e", "#", "define", "F", "f"]
+        );
+        assert_eq!(
+            spelling(input, &[]),
+            vec!["c", "d", "#", "define", "F", "f"]
+        );
+    }
+
+    #[test]
+    fn decided_conditions() {
+        // conditions after a taken group are not lexed
+        let input = "#if 1\nw\n#elif @\n#else @\n#endif\nx";
+        assert_eq!(spelling(input, &[]), vec!["w", "x"]);
+    }
+
+    #[test]
+    fn skipped_line_directives() {
+        let options = LexerOptions {
+            line_markers: true,
+            ..LexerOptions::default()
+        };
+        for input in &["#if 0\n#line 100\n#endif\nx", "#if 0\n#line x\n#endif\nx"] {
+            let result = lex_active(input, &HashSet::new(), options).unwrap();
+            assert_eq!(result.tokens[0].token, Token::Identifier(IStr::new("x")));
+            let start = result.tokens[0].span.start;
+            assert_eq!((start.line, start.column), (4, 1));
+        }
+    }
+
+    #[test]
+    fn skipped_spans() {
+        let input = "#if X\nx\n#else\ny\n#endif\n#if 1\n#else\n#endif\nz";
+        let result = active(input, &[]).unwrap();
+        assert_eq!(
+            result.skipped,
+            vec![Span::new(Position::new(6, 2, 1), Position::new(8, 3, 1))]
+        );
+        let tokens = Lexer::lex_spanned("y\nz").unwrap();
+        let tokens: Vec<Token> = tokens.into_iter().map(|t| t.token).collect();
+        let active: Vec<Token> = result.tokens.into_iter().map(|t| t.token).collect();
+        assert_eq!(active, tokens);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            active("x\n#if 0\n", &[]),
+            Err(Error::new(
+                ErrorKind::UnterminatedConditional,
+                Position::new(2, 2, 1)
+            ))
+        );
+        assert_eq!(
+            active("#else", &[]),
+            Err(Error::new(
+                ErrorKind::UnmatchedConditional("else".into()),
+                Position::new(0, 1, 1)
+            ))
+        );
+        assert_eq!(
+            active("#if 1\n#else\n#else\n#endif", &[]),
+            Err(Error::new(
+                ErrorKind::ConditionalAfterElse("else".into()),
+                Position::new(12, 3, 1)
+            ))
+        );
+        assert_eq!(
+            active("#if (1\n#endif", &[]),
+            Err(Error::new(
+                ErrorKind::InvalidExpression,
+                Position::new(6, 1, 7)
+            ))
+        );
+        let input = "#if 0x10000000000000000wb\n#endif";
+        assert_eq!(
+            lex_active(input, &HashSet::new(), LexerOptions::for_standard(Standard::C23)).map(|_| ()),
+            Err(Error::new(
+                ErrorKind::IntegerOverflow,
+                Position::new(4, 1, 5)
+            ))
+        );
+    }
+
+    #[test]
+    fn keyword_spellings() {
+        let defined = vec!["bool".to_string()].into_iter().collect();
+        let input = "#if defined bool && !defined _Bool && true\nx\n#endif";
+        let result = lex_active(input, &defined, LexerOptions::for_standard(Standard::C23));
+        let tokens: Vec<Token> = result.unwrap().tokens.into_iter().map(|t| t.token).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier(IStr::new("x")),
+                Token::LineTerminator,
+                Token::EOF
+            ]
+        );
+    }
+}
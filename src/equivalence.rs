@@ -1,3 +1,4 @@
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Clone, Copy, Debug)]
 pub enum Equivalence {
     HELL, // nonsense
@@ -27,6 +28,7 @@ pub enum Equivalence {
     InclusiveOr,  // |
     ExclusiveOr,  // ^
     Mod,          // %
+    Hash,         // #
     Char,         // "
     String,       // '
     EightNine,    // 8-9
@@ -46,16 +48,21 @@ pub enum Equivalence {
     F,            // f F
     X,            // x X
     O,            // o O
+    P,            // p P
     BackTick,     // `
     Letter,       // A-Za-z_
+    Eof,          // virtual character past the end of input
+    // ' between digits of a number since C23, the table classifies ' as
+    // `Char` and the lexer picks this class instead while lexing a number
+    DigitSeparator,
 }
 
-type EquivalenceTable = [Equivalence; 255];
+type EquivalenceTable = [Equivalence; 256];
 
 pub const EQUIVALENCE_CLASS: EquivalenceTable = gen_equivalence();
 
 const fn gen_equivalence() -> EquivalenceTable {
-    let mut res = [Equivalence::HELL; 255];
+    let mut res = [Equivalence::HELL; 256];
     res = ident(res);
     res = white_space(res);
     res = line_terminator(res);
@@ -107,6 +114,7 @@ const fn punctuators(mut res: EquivalenceTable) -> EquivalenceTable {
     res['>' as usize] = Equivalence::Gt;
     res['|' as usize] = Equivalence::InclusiveOr;
     res['~' as usize] = Equivalence::Tilde;
+    res['#' as usize] = Equivalence::Hash;
     res
 }
 
@@ -135,6 +143,8 @@ const fn numbers(mut res: EquivalenceTable) -> EquivalenceTable {
     res['f' as usize] = Equivalence::F;
     res['o' as usize] = Equivalence::O;
     res['O' as usize] = Equivalence::O;
+    res['p' as usize] = Equivalence::P;
+    res['P' as usize] = Equivalence::P;
     res['X' as usize] = Equivalence::X;
     res['x' as usize] = Equivalence::X;
     res
@@ -1,24 +1,317 @@
-use std::{error, fmt, num};
+use crate::{source_map::trigraph, span::Position, token::Encoding};
+use internship::IStr;
+use std::{error, fmt};
 
 /// Error that is returned if lexer fails
-#[derive(Debug)]
-pub enum Error {
-    /// Lexer failed to process all input
-    LexingIncomplete,
-    /// Lexer failed for unknow reasons
-    InternalError(Box<error::Error>),
+#[derive(PartialEq, Debug, Clone)]
+pub struct Error {
+    /// What went wrong
+    pub kind: ErrorKind,
+    /// Where in the source it went wrong
+    pub position: Position,
+    /// File name given by the last `#line` directive or linemarker
+    /// before the error when they are handled, see
+    /// [`LexerOptions::line_markers`](crate::options::LexerOptions::line_markers)
+    pub file: Option<IStr>,
+}
+
+/// Reason of lexer failure
+#[derive(PartialEq, Debug, Clone)]
+pub enum ErrorKind {
+    /// String literal is missing closing `"` before end of line
+    UnterminatedString,
+    /// Character constant is missing closing `'` before end of line
+    UnterminatedChar,
+    /// Character constant without any characters, `''`
+    EmptyCharConstant,
+    /// Block comment is missing closing `*/`
+    UnterminatedComment,
+    /// Unknown or malformed escape sequence, contains the escape as written
+    InvalidEscape(String),
+    /// Octal or hexadecimal escape sequence doesn't fit the character
+    /// type, contains the escape as written
+    EscapeOutOfRange(String),
+    /// Character constant has more characters than its type allows
+    CharConstantTooLong,
+    /// Plain character constant with more than one character when those
+    /// are rejected by [`MultiChar::Reject`](crate::options::MultiChar::Reject)
+    MultiCharConstant,
+    /// Adjacent string literals with different encoding prefixes can't
+    /// be concatenated
+    IncompatibleEncodings(Encoding, Encoding),
+    /// Digit is not valid for the radix of numeric literal
+    InvalidDigit { digit: char, radix: u8 },
+    /// Numeric literal has no digits where some are required
+    MissingDigits,
+    /// Hexadecimal floating constant is missing its `p` exponent
+    MissingExponent,
+    /// Numeric literal has a suffix that is not allowed for it
+    InvalidSuffix(String),
+    /// Integer literal is too large to be represented
+    IntegerOverflow,
+    /// Character that can't start any token
+    StrayCharacter(char),
+    /// Trigraph `??c` was replaced, contains `c`
+    Trigraph(char),
+    /// Trigraph `??c` was left as is because trigraphs are disabled,
+    /// contains `c`
+    IgnoredTrigraph(char),
+    /// Unknown preprocessing directive, contains its name
+    InvalidDirective(String),
+    /// Directive requires a macro name which is missing
+    MissingMacroName,
+    /// Malformed parameter list of function-like macro
+    InvalidMacroParameters,
+    /// `#` in function-like macro is not followed by a parameter
+    StringifyNonParameter,
+    /// `##` at the start or end of macro replacement list
+    PasteAtEdge,
+    /// `##` formed something that is not a single token, contains
+    /// spelling of the result
+    InvalidPaste(String),
+    /// Function-like macro invoked with wrong number of arguments
+    MacroArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// Arguments of function-like macro are missing closing `)`
+    UnterminatedMacroCall(String),
+    /// Macro is defined again differently, contains its name
+    MacroRedefined(String),
+    /// `#include` is not followed by a header name
+    InvalidInclude,
+    /// Resolver didn't find the included file, contains its name
+    IncludeNotFound(String),
+    /// Files include each other too deeply
+    IncludeDepth,
+    /// Error in included file, contains name of the file and the error,
+    /// the position is where the file is included
+    InInclude(String, Box<Error>),
+    /// Conditional directive like `#else` without matching `#if`,
+    /// contains its name
+    UnmatchedConditional(String),
+    /// `#elif` or `#else` after `#else`, contains its name
+    ConditionalAfterElse(String),
+    /// `#if` without matching `#endif`
+    UnterminatedConditional,
+    /// Malformed expression of `#if` or `#elif`
+    InvalidExpression,
+    /// Division by zero in expression of `#if` or `#elif`
+    DivisionByZero,
+    /// `#line` is not followed by line number and optional file name
+    InvalidLineDirective,
+    /// `#error` directive, contains its message
+    ErrorDirective(String),
+    /// `#warning` directive, contains its message
+    WarningDirective(String),
+}
+
+impl Error {
+    /// Create new error
+    #[inline]
+    pub fn new(kind: ErrorKind, position: Position) -> Self {
+        Self {
+            kind,
+            position,
+            file: None,
+        }
+    }
+
+    /// Create error knowing only the byte offset, line and column are
+    /// filled in later by `locate`
+    #[inline]
+    pub(crate) fn at(kind: ErrorKind, offset: usize) -> Self {
+        Self::new(kind, Position::new(offset, 0, 0))
+    }
+
+    /// Move error created relative to a part of input to be relative
+    /// to the whole input, `base` is the offset of that part
+    #[inline]
+    pub(crate) fn shift(mut self, base: usize) -> Self {
+        self.position.offset += base;
+        self
+    }
+
+    /// Compute line and column of the error, `from` must not be past
+    /// the offset of the error
+    #[inline]
+    pub(crate) fn locate(mut self, input: &[u8], from: Position) -> Self {
+        let mut position = from;
+        position.advance(input, self.position.offset);
+        self.position = position;
+        self
+    }
 }
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self)
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+        }
+        write!(
+            f,
+            "{}:{}: {}",
+            self.position.line, self.position.column, self.kind
+        )
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::UnterminatedString => write!(f, "missing terminating \" character"),
+            ErrorKind::UnterminatedChar => write!(f, "missing terminating ' character"),
+            ErrorKind::EmptyCharConstant => write!(f, "empty character constant"),
+            ErrorKind::UnterminatedComment => write!(f, "unterminated comment"),
+            ErrorKind::InvalidEscape(escape) => write!(f, "invalid escape sequence `{}`", escape),
+            ErrorKind::EscapeOutOfRange(escape) => {
+                write!(f, "escape sequence `{}` out of range", escape)
+            }
+            ErrorKind::CharConstantTooLong => write!(f, "character constant too long for its type"),
+            ErrorKind::MultiCharConstant => write!(f, "multi-character character constant"),
+            ErrorKind::IncompatibleEncodings(first, second) => write!(
+                f,
+                "concatenation of `{}\"\"` and `{}\"\"` string literals",
+                first.prefix(),
+                second.prefix()
+            ),
+            ErrorKind::InvalidDigit { digit, radix } => {
+                write!(f, "invalid digit `{}` in base {} constant", digit, radix)
+            }
+            ErrorKind::MissingDigits => write!(f, "numeric literal has no digits"),
+            ErrorKind::MissingExponent => {
+                write!(f, "hexadecimal floating constant requires an exponent")
+            }
+            ErrorKind::InvalidSuffix(suffix) => {
+                write!(f, "invalid suffix `{}` on numeric literal", suffix)
+            }
+            ErrorKind::IntegerOverflow => write!(f, "integer literal is too large"),
+            ErrorKind::StrayCharacter(c) => write!(f, "stray `{}` in program", c),
+            ErrorKind::Trigraph(c) => write!(
+                f,
+                "trigraph `??{}` converted to `{}`",
+                c,
+                char::from(trigraph(*c as u8).unwrap_or(b'?'))
+            ),
+            ErrorKind::IgnoredTrigraph(c) => write!(
+                f,
+                "trigraph `??{}` ignored, enable trigraphs to convert it",
+                c
+            ),
+            ErrorKind::InvalidDirective(name) => {
+                write!(f, "invalid preprocessing directive `#{}`", name)
+            }
+            ErrorKind::MissingMacroName => write!(f, "macro name missing"),
+            ErrorKind::InvalidMacroParameters => write!(f, "invalid macro parameter list"),
+            ErrorKind::StringifyNonParameter => {
+                write!(f, "`#` is not followed by a macro parameter")
+            }
+            ErrorKind::PasteAtEdge => write!(
+                f,
+                "`##` cannot appear at either end of a macro expansion"
+            ),
+            ErrorKind::InvalidPaste(spelling) => write!(
+                f,
+                "pasting forms `{}`, an invalid preprocessing token",
+                spelling
+            ),
+            ErrorKind::MacroArgumentCount {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "macro `{}` requires {} arguments, but {} given",
+                name, expected, found
+            ),
+            ErrorKind::UnterminatedMacroCall(name) => {
+                write!(f, "unterminated argument list invoking macro `{}`", name)
+            }
+            ErrorKind::MacroRedefined(name) => write!(f, "`{}` redefined", name),
+            ErrorKind::InvalidInclude => {
+                write!(f, "`#include` expects \"FILENAME\" or <FILENAME>")
+            }
+            ErrorKind::IncludeNotFound(name) => write!(f, "`{}` file not found", name),
+            ErrorKind::IncludeDepth => write!(f, "`#include` nested too deeply"),
+            ErrorKind::InInclude(file, error) => {
+                write!(f, "in file included from here, {}:{}", file, error)
+            }
+            ErrorKind::UnmatchedConditional(name) => write!(f, "`#{}` without `#if`", name),
+            ErrorKind::ConditionalAfterElse(name) => write!(f, "`#{}` after `#else`", name),
+            ErrorKind::UnterminatedConditional => write!(f, "unterminated conditional directive"),
+            ErrorKind::InvalidExpression => {
+                write!(f, "invalid integer constant expression in `#if`")
+            }
+            ErrorKind::DivisionByZero => write!(f, "division by zero in `#if`"),
+            ErrorKind::InvalidLineDirective => write!(
+                f,
+                "`#line` expects a line number and optional file name"
+            ),
+            ErrorKind::ErrorDirective(message) => write!(f, "#error {}", message),
+            ErrorKind::WarningDirective(message) => write!(f, "#warning {}", message),
+        }
     }
 }
 
 impl error::Error for Error {}
 
-impl From<num::ParseIntError> for Error {
-    fn from(o: num::ParseIntError) -> Error {
-        Error::InternalError(Box::new(o))
+/// Suspicious input that is still lexed
+#[derive(PartialEq, Debug, Clone)]
+pub struct Warning {
+    /// What looks wrong, uses the same kinds as errors
+    pub kind: ErrorKind,
+    /// Where in the source it is
+    pub position: Position,
+}
+
+impl Warning {
+    /// Create new warning
+    #[inline]
+    pub fn new(kind: ErrorKind, position: Position) -> Self {
+        Self { kind, position }
+    }
+
+    /// Create warning knowing only the byte offset, line and column are
+    /// filled in later by `locate`
+    #[inline]
+    pub(crate) fn at(kind: ErrorKind, offset: usize) -> Self {
+        Self::new(kind, Position::new(offset, 0, 0))
+    }
+
+    /// Compute line and column of the warning, `from` must not be past
+    /// the offset of the warning
+    #[inline]
+    pub(crate) fn locate(&mut self, input: &[u8], from: Position) {
+        let mut position = from;
+        position.advance(input, self.position.offset);
+        self.position = position;
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: warning: {}",
+            self.position.line, self.position.column, self.kind
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display() {
+        let e = Error::new(
+            ErrorKind::InvalidDigit {
+                digit: '9',
+                radix: 8,
+            },
+            Position::new(12, 3, 7),
+        );
+        assert_eq!(e.to_string(), "3:7: invalid digit `9` in base 8 constant");
     }
 }
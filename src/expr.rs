@@ -0,0 +1,387 @@
+//! Evaluation of controlling expressions of `#if` and `#elif`.
+//!
+//! Expressions are evaluated in `intmax_t` and `uintmax_t`, which are
+//! 64 bits wide. Identifiers and keywords left after macro expansion
+//! are `0`.
+
+use crate::{
+    conditional::DirectiveToken,
+    error::{Error, ErrorKind},
+    span::Position,
+    token::{Number, Token},
+};
+
+// Value of expression together with its signedness
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Value {
+    bits: u64,
+    unsigned: bool,
+}
+
+impl Value {
+    #[inline]
+    fn signed(value: i64) -> Self {
+        Self {
+            bits: value as u64,
+            unsigned: false,
+        }
+    }
+
+    #[inline]
+    fn truth(condition: bool) -> Self {
+        Self::signed(i64::from(condition))
+    }
+
+    #[inline]
+    fn is_true(self) -> bool {
+        self.bits != 0
+    }
+}
+
+struct Parser<'a, T, F> {
+    tokens: &'a [T],
+    pos: usize,
+    // position reported for errors past the last token
+    end: Position,
+    defined: F,
+}
+
+// Binary operators from lowest to highest precedence
+const LEVELS: &[&[Token]] = &[
+    &[Token::OrOp],
+    &[Token::AndOp],
+    &[Token::InclusiveOr],
+    &[Token::ExclusiveOr],
+    &[Token::SingleAnd],
+    &[Token::EqOp, Token::NeOp],
+    &[Token::Lt, Token::Gt, Token::LeOp, Token::GeOp],
+    &[Token::LeftOp, Token::RightOp],
+    &[Token::Plus, Token::Minus],
+    &[Token::Multi, Token::Slash, Token::Mod],
+];
+
+impl<'a, T: DirectiveToken, F: Fn(&str) -> bool> Parser<'a, T, F> {
+    #[inline]
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos).map(T::token)
+    }
+
+    #[inline]
+    fn name(&self) -> Option<&'a str> {
+        self.tokens.get(self.pos).and_then(T::name)
+    }
+
+    #[inline]
+    fn error(&self, kind: ErrorKind) -> Error {
+        let position = self.tokens.get(self.pos).map_or(self.end, |t| t.span().start);
+        Error::new(kind, position)
+    }
+
+    #[inline]
+    fn expect(&mut self, token: &Token) -> Result<(), Error> {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(ErrorKind::InvalidExpression))
+        }
+    }
+
+    // comma expression, operands are only evaluated if `live` is set
+    fn expression(&mut self, live: bool) -> Result<Value, Error> {
+        let mut value = self.conditional(live)?;
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            value = self.conditional(live)?;
+        }
+        Ok(value)
+    }
+
+    fn conditional(&mut self, live: bool) -> Result<Value, Error> {
+        let condition = self.binary(0, live)?;
+        if self.peek() != Some(&Token::QuestionMark) {
+            return Ok(condition);
+        }
+        self.pos += 1;
+        let then = self.expression(live && condition.is_true())?;
+        self.expect(&Token::Colon)?;
+        let otherwise = self.conditional(live && !condition.is_true())?;
+        let value = if condition.is_true() { then } else { otherwise };
+        Ok(Value {
+            bits: value.bits,
+            unsigned: then.unsigned || otherwise.unsigned,
+        })
+    }
+
+    fn binary(&mut self, level: usize, live: bool) -> Result<Value, Error> {
+        if level == LEVELS.len() {
+            return self.unary(live);
+        }
+        let mut lhs = self.binary(level + 1, live)?;
+        while let Some(op) = self.peek().filter(|op| LEVELS[level].contains(op)) {
+            let at = self.pos;
+            self.pos += 1;
+            // right operand of `&&` and `||` is evaluated only if needed
+            let rhs_live = match op {
+                Token::AndOp => live && lhs.is_true(),
+                Token::OrOp => live && !lhs.is_true(),
+                _ => live,
+            };
+            let rhs = self.binary(level + 1, rhs_live)?;
+            lhs = apply(op, lhs, rhs, live)
+                .map_err(|kind| Error::new(kind, self.tokens[at].span().start))?;
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self, live: bool) -> Result<Value, Error> {
+        let op = match self.peek() {
+            Some(op @ Token::Plus)
+            | Some(op @ Token::Minus)
+            | Some(op @ Token::Tilde)
+            | Some(op @ Token::Exclamation) => op,
+            _ => return self.primary(live),
+        };
+        self.pos += 1;
+        let value = self.unary(live)?;
+        Ok(match op {
+            Token::Minus => Value {
+                bits: value.bits.wrapping_neg(),
+                ..value
+            },
+            Token::Tilde => Value {
+                bits: !value.bits,
+                ..value
+            },
+            Token::Exclamation => Value::truth(!value.is_true()),
+            _ => value,
+        })
+    }
+
+    fn primary(&mut self, live: bool) -> Result<Value, Error> {
+        let token = self
+            .peek()
+            .ok_or_else(|| self.error(ErrorKind::InvalidExpression))?;
+        let value = match token {
+            // `wb` constants may be wider than `uintmax_t`
+            Token::NumericLiteral(Number::Integer(integer)) if integer.value > u64::MAX as u128 => {
+                return Err(self.error(ErrorKind::IntegerOverflow));
+            }
+            Token::NumericLiteral(Number::Integer(integer)) => Value {
+                bits: integer.value as u64,
+                // constants that don't fit `intmax_t` are `uintmax_t`
+                unsigned: integer.suffix.unsigned || integer.value > i64::MAX as u128,
+            },
+            Token::CharLiteral(c) => Value::signed(c.value),
+            // C23 `true` is the only keyword which isn't `0`
+            Token::TRUE => Value::signed(1),
+            Token::LParen => {
+                self.pos += 1;
+                let value = self.expression(live)?;
+                self.expect(&Token::RParen)?;
+                return Ok(value);
+            }
+            Token::Identifier(name) if &**name == "defined" => {
+                self.pos += 1;
+                return self.defined();
+            }
+            _ if self.name().is_some() => Value::signed(0),
+            _ => return Err(self.error(ErrorKind::InvalidExpression)),
+        };
+        self.pos += 1;
+        Ok(value)
+    }
+
+    // operand of `defined`, either `name` or `(name)`
+    fn defined(&mut self) -> Result<Value, Error> {
+        let parenthesized = self.peek() == Some(&Token::LParen);
+        if parenthesized {
+            self.pos += 1;
+        }
+        let name = self
+            .name()
+            .ok_or_else(|| self.error(ErrorKind::MissingMacroName))?;
+        let value = Value::truth((self.defined)(name));
+        self.pos += 1;
+        if parenthesized {
+            self.expect(&Token::RParen)?;
+        }
+        Ok(value)
+    }
+}
+
+// Apply binary operator after the usual arithmetic conversions
+fn apply(op: &Token, lhs: Value, rhs: Value, live: bool) -> Result<Value, ErrorKind> {
+    let unsigned = lhs.unsigned || rhs.unsigned;
+    let (a, b) = (lhs.bits, rhs.bits);
+    let ordering = if unsigned {
+        a.cmp(&b)
+    } else {
+        (a as i64).cmp(&(b as i64))
+    };
+    let arithmetic = |bits: u64| Value { bits, unsigned };
+    Ok(match op {
+        Token::OrOp => Value::truth(lhs.is_true() || rhs.is_true()),
+        Token::AndOp => Value::truth(lhs.is_true() && rhs.is_true()),
+        Token::InclusiveOr => arithmetic(a | b),
+        Token::ExclusiveOr => arithmetic(a ^ b),
+        Token::SingleAnd => arithmetic(a & b),
+        Token::EqOp => Value::truth(a == b),
+        Token::NeOp => Value::truth(a != b),
+        Token::Lt => Value::truth(ordering.is_lt()),
+        Token::LeOp => Value::truth(ordering.is_le()),
+        Token::Gt => Value::truth(ordering.is_gt()),
+        Token::GeOp => Value::truth(ordering.is_ge()),
+        // shifts have the type of the left operand
+        Token::LeftOp => Value {
+            bits: a.wrapping_shl(b as u32),
+            ..lhs
+        },
+        Token::RightOp if lhs.unsigned => Value {
+            bits: a.wrapping_shr(b as u32),
+            ..lhs
+        },
+        Token::RightOp => Value {
+            bits: (a as i64).wrapping_shr(b as u32) as u64,
+            ..lhs
+        },
+        Token::Plus => arithmetic(a.wrapping_add(b)),
+        Token::Minus => arithmetic(a.wrapping_sub(b)),
+        Token::Multi => arithmetic(a.wrapping_mul(b)),
+        Token::Slash | Token::Mod if b == 0 => {
+            if live {
+                return Err(ErrorKind::DivisionByZero);
+            }
+            arithmetic(0)
+        }
+        Token::Slash if unsigned => arithmetic(a / b),
+        Token::Slash => arithmetic((a as i64).wrapping_div(b as i64) as u64),
+        Token::Mod if unsigned => arithmetic(a % b),
+        Token::Mod => arithmetic((a as i64).wrapping_rem(b as i64) as u64),
+        _ => unreachable!("not a binary operator"),
+    })
+}
+
+/// Evaluate controlling expression, `defined` tells whether a macro is
+/// defined and `end` is reported for errors at the end of expression
+pub fn evaluate<T: DirectiveToken, F: Fn(&str) -> bool>(
+    tokens: &[T],
+    end: Position,
+    defined: F,
+) -> Result<bool, Error> {
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        end,
+        defined,
+    };
+    let value = parser.expression(true)?;
+    if parser.pos < tokens.len() {
+        return Err(parser.error(ErrorKind::InvalidExpression));
+    }
+    Ok(value.is_true())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{identifier::keyword_spelling, span::Span, token::SpannedToken, Lexer};
+
+    // token with the keyword it is spelled as
+    struct Spelled(SpannedToken, Option<&'static str>);
+
+    impl DirectiveToken for Spelled {
+        fn token(&self) -> &Token {
+            &self.0.token
+        }
+
+        fn span(&self) -> Span {
+            self.0.span
+        }
+
+        fn line_start(&self) -> bool {
+            self.0.line_start
+        }
+
+        fn name(&self) -> Option<&str> {
+            match &self.0.token {
+                Token::Identifier(name) => Some(name),
+                _ => self.1,
+            }
+        }
+    }
+
+    fn eval(input: &str) -> Result<bool, Error> {
+        let mut tokens = Lexer::lex_spanned(input).unwrap();
+        let end = tokens.pop().unwrap().span.start;
+        let tokens: Vec<Spelled> = tokens
+            .into_iter()
+            .map(|t| {
+                let keyword = keyword_spelling(&input[t.span.start.offset..t.span.end.offset]);
+                Spelled(t, keyword)
+            })
+            .collect();
+        evaluate(&tokens, end, |name| name == "FOO")
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(eval("1 + 2 * 3 == 7"), Ok(true));
+        assert_eq!(eval("(1 + 2) * 3 == 7"), Ok(false));
+        assert_eq!(eval("-1 < 0 && -1 > 0u"), Ok(true));
+        assert_eq!(eval("~0u == 0xffffffffffffffff"), Ok(true));
+        assert_eq!(eval("-7 / 2 == -3 && -7 % 2 == -1"), Ok(true));
+        assert_eq!(eval("1 << 62 >> 61 == 2 && -8 >> 1 == -4"), Ok(true));
+        assert_eq!(eval("'a' == 97 && '\\377' < 0"), Ok(true));
+        assert_eq!(eval("(2 || 1 / 0) && !(0 && 1 / 0)"), Ok(true));
+        assert_eq!(eval("1 ? 2 : (1 / 0)"), Ok(true));
+        assert_eq!(eval("(0 ? 1u : -1) > 0"), Ok(true));
+        assert_eq!(eval("(1, 0)"), Ok(false));
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(eval("defined FOO && defined(FOO)"), Ok(true));
+        assert_eq!(eval("defined BAR || BAR || int"), Ok(false));
+        assert_eq!(eval("!defined(int)"), Ok(true));
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            eval("1 +"),
+            Err(Error::new(
+                ErrorKind::InvalidExpression,
+                Position::new(3, 1, 4)
+            ))
+        );
+        assert_eq!(
+            eval("(1 2"),
+            Err(Error::new(
+                ErrorKind::InvalidExpression,
+                Position::new(3, 1, 4)
+            ))
+        );
+        assert_eq!(
+            eval("2 / (1 - 1)"),
+            Err(Error::new(
+                ErrorKind::DivisionByZero,
+                Position::new(2, 1, 3)
+            ))
+        );
+        assert_eq!(
+            eval("1.5"),
+            Err(Error::new(
+                ErrorKind::InvalidExpression,
+                Position::new(0, 1, 1)
+            ))
+        );
+        assert_eq!(
+            eval("defined()"),
+            Err(Error::new(
+                ErrorKind::MissingMacroName,
+                Position::new(8, 1, 9)
+            ))
+        );
+    }
+}
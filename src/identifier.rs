@@ -1,6 +1,9 @@
-use crate::token::Token;
+use crate::{
+    options::{LexerOptions, Standard},
+    symbols::{Symbol, SymbolTable},
+    token::Token,
+};
 use internship::IStr;
-use std::str;
 
 const KEYWORDS: phf::Map<&'static str, Token> = phf_map! {
     "auto" => Token::AUTO,
@@ -48,40 +51,139 @@ const KEYWORDS: phf::Map<&'static str, Token> = phf_map! {
     "_Static_assert" => Token::StaticAssert,
     "_Thread_local" => Token::ThreadLocal,
     "__func__" => Token::FuncName,
+    "alignas" => Token::ALIGNAS,
+    "alignof" => Token::ALIGNOF,
+    "bool" => Token::BOOL,
+    "constexpr" => Token::CONSTEXPR,
+    "false" => Token::FALSE,
+    "nullptr" => Token::NULLPTR,
+    "static_assert" => Token::StaticAssert,
+    "thread_local" => Token::ThreadLocal,
+    "true" => Token::TRUE,
+    "typeof" => Token::TYPEOF,
+    "typeof_unqual" => Token::TypeofUnqual,
+    "_BitInt" => Token::BitInt,
+    "_Decimal32" => Token::DECIMAL32,
+    "_Decimal64" => Token::DECIMAL64,
+    "_Decimal128" => Token::DECIMAL128,
+    "asm" => Token::ASM,
+    "__alignof" => Token::ALIGNOF,
+    "__alignof__" => Token::ALIGNOF,
+    "__asm" => Token::ASM,
+    "__asm__" => Token::ASM,
+    "__attribute" => Token::ATTRIBUTE,
+    "__attribute__" => Token::ATTRIBUTE,
+    "__auto_type" => Token::AutoType,
+    "__builtin_va_arg" => Token::BuiltinVaArg,
+    "__const" => Token::CONST,
+    "__const__" => Token::CONST,
+    "__extension__" => Token::EXTENSION,
+    "__inline" => Token::INLINE,
+    "__inline__" => Token::INLINE,
+    "__int128" => Token::INT128,
+    "__label__" => Token::LABEL,
+    "__restrict" => Token::RESTRICT,
+    "__restrict__" => Token::RESTRICT,
+    "__signed" => Token::SIGNED,
+    "__signed__" => Token::SIGNED,
+    "__typeof" => Token::TYPEOF,
+    "__typeof__" => Token::TYPEOF,
+    "__volatile" => Token::VOLATILE,
+    "__volatile__" => Token::VOLATILE,
+    "_Float128" => Token::FLOAT128,
+    "_cdecl" => Token::CDECL,
+    "_fastcall" => Token::FASTCALL,
+    "_stdcall" => Token::STDCALL,
+    "__cdecl" => Token::CDECL,
+    "__declspec" => Token::DECLSPEC,
+    "__except" => Token::EXCEPT,
+    "__fastcall" => Token::FASTCALL,
+    "__finally" => Token::FINALLY,
+    "__forceinline" => Token::FORCEINLINE,
+    "__int8" => Token::INT8,
+    "__int16" => Token::INT16,
+    "__int32" => Token::INT32,
+    "__int64" => Token::INT64,
+    "__ptr32" => Token::PTR32,
+    "__ptr64" => Token::PTR64,
+    "__stdcall" => Token::STDCALL,
+    "__try" => Token::TRY,
 };
 
+// Whether name is a keyword in the selected standard. GNU mode has the
+// ones spelled with reserved identifiers, `inline` and `typeof` in all
+// standards, GNU and MSVC modes have their own extensions.
+#[inline]
+fn is_keyword(name: &str, options: &LexerOptions) -> bool {
+    let since = match name {
+        "__alignof" | "__inline" | "__restrict" => return options.gnu || options.msvc,
+        "asm" | "__alignof__" | "__asm" | "__asm__" | "__attribute" | "__attribute__"
+        | "__auto_type" | "__builtin_va_arg" | "__const" | "__const__" | "__extension__"
+        | "__inline__" | "__int128" | "__label__" | "__restrict__" | "__signed"
+        | "__signed__" | "__typeof" | "__typeof__" | "__volatile" | "__volatile__"
+        | "_Float128" => return options.gnu,
+        "_cdecl" | "_fastcall" | "_stdcall" | "__cdecl" | "__declspec" | "__except"
+        | "__fastcall" | "__finally" | "__forceinline" | "__int8" | "__int16" | "__int32"
+        | "__int64" | "__ptr32" | "__ptr64" | "__stdcall" | "__try" => return options.msvc,
+        "inline" | "restrict" | "_Bool" | "_Complex" | "_Imaginary" | "__func__" => Standard::C99,
+        "_Alignas" | "_Alignof" | "_Atomic" | "_Generic" | "_Noreturn" | "_Static_assert"
+        | "_Thread_local" => Standard::C11,
+        "alignas" | "alignof" | "bool" | "constexpr" | "false" | "nullptr" | "static_assert"
+        | "thread_local" | "true" | "typeof" | "typeof_unqual" | "_BitInt" | "_Decimal32"
+        | "_Decimal64" | "_Decimal128" => Standard::C23,
+        _ => Standard::C89,
+    };
+    options.standard >= since
+        || options.gnu && (name.starts_with('_') || name == "inline" || name == "typeof")
+}
+
 #[inline]
 fn is_identifier_part(cp: u8) -> bool {
     cp == 0x24
         || cp == 0x5F
-        || (cp >= 0x41 && cp <= 0x5A)
-        || (cp >= 0x61 && cp <= 0x7A)
-        || (cp >= 0x30 && cp <= 0x39)
+        || (0x41..=0x5A).contains(&cp)
+        || (0x61..=0x7A).contains(&cp)
+        || (0x30..=0x39).contains(&cp)
         || cp == 0x5C
         || cp >= 0x80
 }
 
 #[inline]
-pub fn parse_identifier(input: &[u8], c_src: &mut usize) -> Token {
-    let mut it = 0;
-    for i in 0..input.len() - *c_src {
-        if !unsafe { is_identifier_part(*input.get_unchecked(*c_src + i)) } {
-            it = i;
-            break;
-        }
-    }
-    let ident = &input[*c_src - 1..*c_src + it];
+pub fn parse_identifier(
+    input: &[u8],
+    c_src: &mut usize,
+    options: &LexerOptions,
+    symbols: &SymbolTable,
+) -> Token {
+    // identifier may run up to the end of input
+    let it = input[*c_src..]
+        .iter()
+        .position(|cp| !is_identifier_part(*cp))
+        .unwrap_or(input.len() - *c_src);
+    let ident = String::from_utf8_lossy(&input[*c_src - 1..*c_src + it]);
     *c_src += it;
-    let ident = unsafe { str::from_utf8_unchecked(ident) };
-    KEYWORDS
-        .get(ident)
-        .cloned()
-        .unwrap_or_else(|| Token::Identifier(IStr::new(ident)))
+    if let Some(keyword) = KEYWORDS.get(&*ident).filter(|_| is_keyword(&ident, options)) {
+        return keyword.clone();
+    }
+    let name = IStr::new(&ident);
+    match symbols.lookup(&ident) {
+        Some(Symbol::Typedef) => Token::TypedefName(name),
+        Some(Symbol::EnumConstant) => Token::EnumerationConstant(name),
+        Some(Symbol::Ordinary) | None => Token::Identifier(name),
+    }
+}
+
+/// Keyword spelled as `spelling` in some standard or dialect, keywords
+/// with several spellings like `bool` and `_Bool` are told apart by it
+#[inline]
+pub fn keyword_spelling(spelling: &str) -> Option<&'static str> {
+    KEYWORDS.get_entry(spelling).map(|(name, _)| *name)
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::*;
+    use crate::options::{LexerOptions, Standard};
     use ::internship::IStr;
 
     should!(
@@ -106,4 +208,168 @@ mod tests {
     );
 
     should!(keyword, "auto ", vec![Token::AUTO, Token::EOF]);
-}
\ No newline at end of file
+
+    fn standard(standard: Standard, gnu: bool) -> LexerOptions {
+        LexerOptions {
+            gnu,
+            ..LexerOptions::for_standard(standard)
+        }
+    }
+
+    should!(
+        keywords_c89,
+        "inline restrict _Bool __func__ _Static_assert",
+        standard(Standard::C89, false),
+        vec![
+            Token::Identifier(IStr::new("inline")),
+            Token::Identifier(IStr::new("restrict")),
+            Token::Identifier(IStr::new("_Bool")),
+            Token::Identifier(IStr::new("__func__")),
+            Token::Identifier(IStr::new("_Static_assert")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        keywords_gnu89,
+        "inline restrict _Bool __func__ _Static_assert",
+        standard(Standard::C89, true),
+        vec![
+            Token::INLINE,
+            Token::Identifier(IStr::new("restrict")),
+            Token::BOOL,
+            Token::FuncName,
+            Token::StaticAssert,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        keywords_c99,
+        "restrict _Complex _Alignas _Thread_local",
+        standard(Standard::C99, false),
+        vec![
+            Token::RESTRICT,
+            Token::COMPLEX,
+            Token::Identifier(IStr::new("_Alignas")),
+            Token::Identifier(IStr::new("_Thread_local")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        keywords_c23,
+        "bool _Bool true nullptr alignas typeof_unqual _BitInt _Decimal64 thread_local",
+        standard(Standard::C23, false),
+        vec![
+            Token::BOOL,
+            Token::BOOL,
+            Token::TRUE,
+            Token::NULLPTR,
+            Token::ALIGNAS,
+            Token::TypeofUnqual,
+            Token::BitInt,
+            Token::DECIMAL64,
+            Token::ThreadLocal,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        keywords_gnu17,
+        "typeof bool false _BitInt",
+        standard(Standard::C17, true),
+        vec![
+            Token::TYPEOF,
+            Token::Identifier(IStr::new("bool")),
+            Token::Identifier(IStr::new("false")),
+            Token::BitInt,
+            Token::EOF
+        ]
+    );
+
+    #[test]
+    fn keyword_spellings() {
+        use crate::identifier::keyword_spelling;
+
+        assert_eq!(keyword_spelling("while"), Some("while"));
+        assert_eq!(keyword_spelling("_Bool"), Some("_Bool"));
+        assert_eq!(keyword_spelling("x"), None);
+        assert_eq!(keyword_spelling("..."), None);
+    }
+
+    should!(
+        keywords_gnu_extensions,
+        "__attribute__ asm __asm__ __typeof__ __extension__ __inline__ __restrict__ \
+         __builtin_va_arg __label__ __int128 _Float128 __auto_type __volatile__",
+        standard(Standard::C17, true),
+        vec![
+            Token::ATTRIBUTE,
+            Token::ASM,
+            Token::ASM,
+            Token::TYPEOF,
+            Token::EXTENSION,
+            Token::INLINE,
+            Token::RESTRICT,
+            Token::BuiltinVaArg,
+            Token::LABEL,
+            Token::INT128,
+            Token::FLOAT128,
+            Token::AutoType,
+            Token::VOLATILE,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        gnu_extensions_strict,
+        "asm __asm__ __int128",
+        standard(Standard::C23, false),
+        vec![
+            Token::Identifier(IStr::new("asm")),
+            Token::Identifier(IStr::new("__asm__")),
+            Token::Identifier(IStr::new("__int128")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        keywords_msvc,
+        "__declspec(dllexport) __int64 _cdecl __stdcall __fastcall __forceinline __int8 \
+         __ptr64 __try __except __finally __inline __attribute__",
+        LexerOptions {
+            msvc: true,
+            ..LexerOptions::default()
+        },
+        vec![
+            Token::DECLSPEC,
+            Token::LParen,
+            Token::Identifier(IStr::new("dllexport")),
+            Token::RParen,
+            Token::INT64,
+            Token::CDECL,
+            Token::STDCALL,
+            Token::FASTCALL,
+            Token::FORCEINLINE,
+            Token::INT8,
+            Token::PTR64,
+            Token::TRY,
+            Token::EXCEPT,
+            Token::FINALLY,
+            Token::INLINE,
+            Token::Identifier(IStr::new("__attribute__")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        msvc_extensions_gnu,
+        "__int64 __cdecl",
+        standard(Standard::C17, true),
+        vec![
+            Token::Identifier(IStr::new("__int64")),
+            Token::Identifier(IStr::new("__cdecl")),
+            Token::EOF
+        ]
+    );
+}
@@ -1,4 +1,4 @@
-#![feature(proc_macro_hygiene)]
+#![forbid(unsafe_code)]
 
 #[macro_use]
 extern crate phf;
@@ -12,7 +12,17 @@ mod string;
 mod state;
 mod equivalence;
 mod state_machine;
+mod source_map;
+mod expr;
+pub mod comments;
+pub mod concat;
+pub mod conditional;
 pub mod error;
+pub mod options;
+pub mod preprocess;
+pub mod span;
+pub mod symbols;
+pub mod trivia;
 
 /// Module for efficient string representation
 pub mod internship {
@@ -21,17 +31,272 @@ pub mod internship {
     pub use internship::*;
 }
 
-use self::{state_machine::parse, token::*};
+use self::{
+    options::LexerOptions,
+    state_machine::{parse, Tokenizer},
+    token::*,
+};
+use std::collections::{HashSet, VecDeque};
 
 /// Lexer implementation
-#[derive(Debug, Copy, Clone)]
-pub struct Lexer;
+///
+/// Besides lexing whole input at once with [`Lexer::lex`], lexer can be
+/// created with [`Lexer::new`] and used as an iterator that lexes tokens
+/// lazily, one at a time. The iterator ends with `None` instead of
+/// [`Token::EOF`] and stops after the first error.
+#[derive(Debug, Clone)]
+pub struct Lexer<'a> {
+    tokenizer: Tokenizer<'a>,
+    peeked: VecDeque<Result<SpannedToken, error::Error>>,
+    failed: bool,
+}
+
+impl<'a> Lexer<'a> {
+    /// Create lexer over string
+    pub fn new(s: &'a str) -> Self {
+        Self::with_options(s, LexerOptions::default())
+    }
+
+    /// Create lexer over string accepting literal forms selected by
+    /// `options`
+    pub fn with_options(s: &'a str, options: LexerOptions) -> Self {
+        Self {
+            tokenizer: Tokenizer::with_options(s, options),
+            peeked: VecDeque::new(),
+            failed: false,
+        }
+    }
 
-impl Lexer {
     /// Transform string to stream of tokens
     pub fn lex(s: &str) -> Result<Vec<Token>, error::Error> {
-        let mut tokens = parse(s)?;
-        tokens.push(Token::EOF);
-        Ok(tokens)
+        Self::lex_with(s, LexerOptions::default())
+    }
+
+    /// Transform string to stream of tokens of the dialect selected by
+    /// `options`
+    pub fn lex_with(s: &str, options: LexerOptions) -> Result<Vec<Token>, error::Error> {
+        let tokens = parse(s, options)?;
+        Ok(tokens.into_iter().map(|t| t.token).collect())
+    }
+
+    /// Transform string to stream of tokens annotated with their
+    /// location in the source
+    pub fn lex_spanned(s: &str) -> Result<Vec<SpannedToken>, error::Error> {
+        Self::lex_spanned_with(s, LexerOptions::default())
+    }
+
+    /// Transform string to stream of tokens of the dialect selected by
+    /// `options` annotated with their location in the source
+    pub fn lex_spanned_with(
+        s: &str,
+        options: LexerOptions,
+    ) -> Result<Vec<SpannedToken>, error::Error> {
+        parse(s, options)
+    }
+
+    /// Transform string to stream of tokens with adjacent string literals
+    /// concatenated, see [`concat`]
+    pub fn lex_concatenated(s: &str) -> Result<Vec<concat::ConcatenatedToken>, error::Error> {
+        Self::lex_concatenated_with(s, LexerOptions::default())
+    }
+
+    /// Transform string to stream of tokens of the dialect selected by
+    /// `options` with adjacent string literals concatenated
+    pub fn lex_concatenated_with(
+        s: &str,
+        options: LexerOptions,
+    ) -> Result<Vec<concat::ConcatenatedToken>, error::Error> {
+        concat::concatenate(parse(s, options)?, &options)
+    }
+
+    /// Transform string to stream of tokens of groups of conditional
+    /// directives that are active when macros in `defined` are defined,
+    /// see [`conditional`]
+    pub fn lex_active(
+        s: &str,
+        defined: &HashSet<String>,
+    ) -> Result<conditional::ActiveTokens, error::Error> {
+        Self::lex_active_with(s, defined, LexerOptions::default())
+    }
+
+    /// Transform string to stream of tokens of the dialect selected by
+    /// `options` of groups of conditional directives that are active
+    /// when macros in `defined` are defined
+    pub fn lex_active_with(
+        s: &str,
+        defined: &HashSet<String>,
+        options: LexerOptions,
+    ) -> Result<conditional::ActiveTokens, error::Error> {
+        conditional::lex_active(s, defined, options)
+    }
+
+    /// Transform string to stream of tokens together with white space
+    /// and comments between them, see [`trivia`]
+    pub fn lex_lossless(s: &str) -> Result<Vec<trivia::LosslessToken>, error::Error> {
+        Self::lex_lossless_with(s, LexerOptions::default())
+    }
+
+    /// Transform string to stream of tokens of the dialect selected by
+    /// `options` together with white space and comments between them
+    pub fn lex_lossless_with(
+        s: &str,
+        options: LexerOptions,
+    ) -> Result<Vec<trivia::LosslessToken>, error::Error> {
+        trivia::lex_lossless(s, options)
+    }
+
+    /// Extract comments from string, documentation comments are
+    /// associated with the tokens they document, see [`comments`]
+    pub fn comments(s: &str) -> Result<Vec<comments::Comment>, error::Error> {
+        Self::comments_with(s, LexerOptions::default())
+    }
+
+    /// Extract comments from string lexed in the dialect selected by
+    /// `options`
+    pub fn comments_with(
+        s: &str,
+        options: LexerOptions,
+    ) -> Result<Vec<comments::Comment>, error::Error> {
+        comments::comments(s, options)
+    }
+
+    /// Lex next token together with its location in the source
+    pub fn next_spanned(&mut self) -> Option<Result<SpannedToken, error::Error>> {
+        self.peeked.pop_front().or_else(|| self.lex_next())
+    }
+
+    /// Look at the next token without consuming it
+    pub fn peek(&mut self) -> Option<Result<&Token, &error::Error>> {
+        self.peek_nth(0)
+    }
+
+    /// Look `n` tokens ahead without consuming anything, `peek_nth(0)` is
+    /// the same as `peek()`
+    pub fn peek_nth(&mut self, n: usize) -> Option<Result<&Token, &error::Error>> {
+        while self.peeked.len() <= n {
+            let token = self.lex_next()?;
+            self.peeked.push_back(token);
+        }
+        self.peeked.get(n).map(|t| t.as_ref().map(|t| &t.token))
+    }
+
+    /// Symbol table the parser declares typedef names and enumeration
+    /// constants in, see [`symbols`]
+    pub fn symbols(&self) -> &symbols::SymbolTable {
+        self.tokenizer.symbols()
+    }
+
+    /// Mutable symbol table, declarations affect classification of
+    /// identifiers lexed after them but not of tokens already peeked
+    pub fn symbols_mut(&mut self) -> &mut symbols::SymbolTable {
+        self.tokenizer.symbols_mut()
+    }
+
+    /// Warnings about tokens lexed so far, including peeked ones
+    pub fn warnings(&self) -> &[error::Warning] {
+        self.tokenizer.warnings()
+    }
+
+    fn lex_next(&mut self) -> Option<Result<SpannedToken, error::Error>> {
+        if self.failed {
+            return None;
+        }
+        let token = self.tokenizer.next_token().transpose();
+        self.failed = matches!(token, Some(Err(_)));
+        token
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token, error::Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_spanned().map(|t| t.map(|t| t.token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{Error, ErrorKind},
+        span::Position,
+    };
+    use ::internship::IStr;
+
+    #[test]
+    fn iterator() {
+        let tokens: Result<Vec<Token>, Error> = Lexer::new("a = 1;").collect();
+        assert_eq!(
+            tokens.unwrap(),
+            vec![
+                Token::Identifier(IStr::new("a")),
+                Token::Assign,
+                Token::NumericLiteral(Number::Integer(Integer::new(
+                    1,
+                    10,
+                    IntegerSuffix::default()
+                ))),
+                Token::Semicolon,
+            ]
+        );
+    }
+
+    #[test]
+    fn iterator_peek() {
+        let mut lexer = Lexer::new("f ( x )");
+        assert_eq!(lexer.peek_nth(1), Some(Ok(&Token::LParen)));
+        assert_eq!(lexer.peek(), Some(Ok(&Token::Identifier(IStr::new("f")))));
+        assert_eq!(lexer.next(), Some(Ok(Token::Identifier(IStr::new("f")))));
+        assert_eq!(lexer.next(), Some(Ok(Token::LParen)));
+        let x = lexer.next_spanned().unwrap().unwrap();
+        assert_eq!(x.span.start, Position::new(4, 1, 5));
+        assert_eq!(lexer.peek(), Some(Ok(&Token::RParen)));
+        assert_eq!(lexer.next(), Some(Ok(Token::RParen)));
+        assert_eq!(lexer.peek(), None);
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn iterator_stops_after_error() {
+        let mut lexer = Lexer::new("a @ b");
+        assert_eq!(lexer.next(), Some(Ok(Token::Identifier(IStr::new("a")))));
+        assert_eq!(
+            lexer.next(),
+            Some(Err(Error::new(
+                ErrorKind::StrayCharacter('@'),
+                Position::new(2, 1, 3)
+            )))
+        );
+        assert_eq!(lexer.next(), None);
+    }
+
+    #[test]
+    fn dialects() {
+        use crate::{options::Standard, token::Encoding};
+
+        let c89 = LexerOptions::for_standard(Standard::C89);
+        let inline = Token::Identifier(IStr::new("inline"));
+        assert_eq!(Lexer::lex_with("inline", c89).unwrap(), vec![inline.clone(), Token::EOF]);
+        assert_eq!(Lexer::lex_spanned_with("inline", c89).unwrap()[0].token, inline);
+        assert!(Lexer::lex_spanned("0b1").is_ok());
+        assert!(Lexer::lex_with("0b1", c89).is_err());
+        assert!(Lexer::lex_lossless_with("0b1", c89).is_err());
+        assert!(Lexer::comments_with("0b1 /** a */", c89).is_err());
+        let defined = HashSet::new();
+        assert!(Lexer::lex_active_with("#if 1\n0b1\n#endif", &defined, c89).is_err());
+        // the promoted piece is encoded like the wide one
+        let short_wchar = LexerOptions {
+            short_wchar: true,
+            ..LexerOptions::default()
+        };
+        let tokens = Lexer::lex_concatenated_with(r#"L"a" "b""#, short_wchar).unwrap();
+        match &tokens[0].token {
+            Token::StringLiteral(literal) => {
+                assert_eq!(literal.encoding, Encoding::Wide);
+                assert_eq!(literal.value, CodeUnits::U16(vec![97, 98]));
+            }
+            token => panic!("{:?} is not a string literal", token),
+        }
     }
 }
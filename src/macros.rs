@@ -10,6 +10,45 @@ macro_rules! should {
             assert_eq!(result, $right);
         }
     };
+    ($name:ident, $left:expr, $options:expr, $right:expr) => {
+        #[test]
+        fn $name() {
+            use crate::Lexer;
+
+            let input = &$left[..];
+            let mut result = Lexer::with_options(input, $options)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            result.push(Token::EOF);
+            assert_eq!(result, $right);
+        }
+    };
+}
+
+#[cfg(test)]
+macro_rules! should_fail {
+    ($name:ident, $left:expr, $right:expr) => {
+        #[test]
+        fn $name() {
+            use crate::Lexer;
+
+            let input = &$left[..];
+            let result = Lexer::lex(input).unwrap_err();
+            assert_eq!(result, $right);
+        }
+    };
+    ($name:ident, $left:expr, $options:expr, $right:expr) => {
+        #[test]
+        fn $name() {
+            use crate::Lexer;
+
+            let input = &$left[..];
+            let result = Lexer::with_options(input, $options)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap_err();
+            assert_eq!(result, $right);
+        }
+    };
 }
 
 macro_rules! accept_state {
@@ -1,207 +1,936 @@
 use crate::{
-    error::Error,
-    token::{Number, Token},
+    error::{Error, ErrorKind},
+    options::{LexerOptions, Standard},
+    token::{
+        DataModel, Float, FloatSuffix, Integer, IntegerLength, IntegerSuffix, Number, Token,
+    },
+};
+use std::{
+    borrow::Cow,
+    num::{IntErrorKind, ParseIntError},
+    str,
 };
-use std::str;
 
+// Digits are already checked by the state machine, so parsing can
+// only fail on missing digits or overflow
 #[inline]
-pub fn parse_number_radix(
-    input: &[u8],
-    c_src: &mut usize,
-    token_len: u64,
-    radix: u8,
-) -> Result<Token, Error> {
-    let i =
-        unsafe { str::from_utf8_unchecked(&input[*c_src - token_len as usize + 2..*c_src - 1]) };
-    let i = u32::from_str_radix(i, u32::from(radix))?;
-    *c_src -= 1;
-    Ok(Token::NumericLiteral(Number::new(i, 0, 1, radix)))
+fn int_error(e: &ParseIntError, offset: usize) -> Error {
+    match e.kind() {
+        IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+            Error::at(ErrorKind::IntegerOverflow, offset)
+        }
+        _ => Error::at(ErrorKind::MissingDigits, offset),
+    }
 }
 
+// Numeric literals are ASCII only, so conversion never fails on
+// slices produced by the state machine
 #[inline]
-pub fn parse_number(input: &[u8], c_src: &mut usize, token_len: u64) -> Result<Token, Error> {
-    let i = unsafe { str::from_utf8_unchecked(&input[*c_src - token_len as usize..*c_src - 1]) };
-    let i = u32::from_str_radix(i, 10)?;
-    *c_src -= 1;
-    Ok(Token::NumericLiteral(Number::new(i, 0, 1, 10)))
+fn ascii(input: &[u8]) -> &str {
+    str::from_utf8(input).unwrap_or_default()
 }
 
+// Text of constant without digit separators, which the state machine
+// only lets in between digits
 #[inline]
-pub fn parse_number_decimal(
+fn without_separators(text: &str) -> Cow<'_, str> {
+    if text.contains('\'') {
+        Cow::Owned(text.replace('\'', ""))
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+// Offset in the source of byte `i` of constant text without digit
+// separators, `raw` is the text as written starting at `start`
+#[inline]
+fn source_offset(raw: &str, start: usize, i: usize) -> usize {
+    raw.bytes()
+        .enumerate()
+        .filter(|(_, b)| *b != b'\'')
+        .nth(i)
+        .map_or(start + raw.len(), |(offset, _)| start + offset)
+}
+
+// Splits `u`, `l`, `ll` suffix in any order and case, `lL` is not allowed.
+// `wb` takes the place of `l` since C23, MSVC has `i64` after optional
+// `u` too.
+#[inline]
+fn integer_suffix(suffix: &str, options: &LexerOptions) -> Option<IntegerSuffix> {
+    fn unsigned(s: &str) -> (bool, &str) {
+        match s.strip_prefix('u').or_else(|| s.strip_prefix('U')) {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        }
+    }
+    fn length(s: &str, bit_precise: bool) -> (IntegerLength, &str) {
+        let wb = s.strip_prefix("wb").or_else(|| s.strip_prefix("WB"));
+        if let Some(rest) = wb.filter(|_| bit_precise) {
+            (IntegerLength::BitPrecise, rest)
+        } else if let Some(rest) = s.strip_prefix("ll").or_else(|| s.strip_prefix("LL")) {
+            (IntegerLength::LongLong, rest)
+        } else if let Some(rest) = s.strip_prefix('l').or_else(|| s.strip_prefix('L')) {
+            (IntegerLength::Long, rest)
+        } else {
+            (IntegerLength::Plain, s)
+        }
+    }
+
+    let bit_precise = options.standard >= Standard::C23;
+    let (u, rest) = unsigned(suffix);
+    if options.msvc && matches!(rest, "i64" | "I64") {
+        return Some(IntegerSuffix::new(u, IntegerLength::LongLong));
+    }
+    let (length, rest) = length(rest, bit_precise);
+    let (u, rest) = if u { (u, rest) } else { unsigned(rest) };
+    if rest.is_empty() {
+        Some(IntegerSuffix::new(u, length))
+    } else {
+        None
+    }
+}
+
+#[inline]
+pub fn parse_integer(
     input: &[u8],
     c_src: &mut usize,
     token_len: u64,
+    options: &LexerOptions,
 ) -> Result<Token, Error> {
-    let mut i_point = 0;
-    for (i, item) in input
-        .iter()
-        .enumerate()
-        .take(*c_src - 1)
-        .skip(*c_src - token_len as usize)
+    let start = *c_src - token_len as usize;
+    let raw = ascii(&input[start..*c_src - 1]);
+    let text = without_separators(raw);
+    let at = |i| source_offset(raw, start, i);
+    // prefixes that are not enabled are left to fail as suffixes, like
+    // `b1` of `0b1`
+    let (radix, digits_start) = match text.as_bytes() {
+        [b'0', b'x' | b'X', ..] => (16, 2),
+        [b'0', b'b' | b'B', ..]
+            if options.binary_literals || options.gnu || options.standard >= Standard::C23 =>
         {
-            if *item == b'.' {
-                i_point = i;
-                break;
-            }
+            (2, 2)
         }
-    let integer = unsafe { str::from_utf8_unchecked(&input[*c_src - token_len as usize..i_point]) };
-    let integer = u32::from_str_radix(integer, 10)?;
-
-    let decimal = unsafe { str::from_utf8_unchecked(&input[i_point + 1..*c_src - 1]) };
-    let decimal = u32::from_str_radix(decimal, 10)?;
+        [b'0', b'o' | b'O', ..] if options.octal_prefix => (8, 2),
+        [b'0', b'0'..=b'9', ..] => (8, 1),
+        _ => (10, 0),
+    };
+    let digits_end = text[digits_start..]
+        .find(|c: char| !c.is_digit(radix.max(10)))
+        .map_or(text.len(), |i| digits_start + i);
+    if let Some(i) = text[digits_start..digits_end].find(|c: char| !c.is_digit(radix)) {
+        let digit = text[digits_start + i..].chars().next().unwrap_or_default();
+        return Err(Error::at(
+            ErrorKind::InvalidDigit {
+                digit,
+                radix: radix as u8,
+            },
+            at(digits_start + i),
+        ));
+    }
 
+    let suffix = &text[digits_end..];
+    let suffix = integer_suffix(suffix, options).ok_or_else(|| {
+        Error::at(
+            ErrorKind::InvalidSuffix(suffix.to_string()),
+            at(digits_end),
+        )
+    })?;
+    let value = u128::from_str_radix(&text[digits_start..digits_end], radix)
+        .map_err(|e| int_error(&e, start))?;
+    let integer = Integer::new(value, radix as u8, suffix);
+    // `long long` is 64 bits wide in every data model, so whether the
+    // constant has any type at all doesn't depend on the target
+    if integer.ty(&DataModel::LP64).is_none() {
+        return Err(Error::at(ErrorKind::IntegerOverflow, start));
+    }
     *c_src -= 1;
-    Ok(Token::NumericLiteral(Number::new(integer, decimal, 1, 10)))
+    Ok(Token::NumericLiteral(Number::Integer(integer)))
 }
 
 #[inline]
-pub fn parse_exponent(input: &[u8], c_src: &mut usize, token_len: u64) -> Result<Token, Error> {
-    let mut i_e = 0;
-    let mut i_point = None;
-    for (i, item) in input
-        .iter()
-        .enumerate()
-        .take(*c_src - 1)
-        .skip(*c_src - token_len as usize)
-        {
-            if *item == b'.' {
-                i_point = Some(i);
+fn float_suffix(suffix: &str) -> Option<FloatSuffix> {
+    match suffix {
+        "" => Some(FloatSuffix::Plain),
+        "f" | "F" => Some(FloatSuffix::Float),
+        "l" | "L" => Some(FloatSuffix::Long),
+        _ => None,
+    }
+}
+
+/// Exact power of two, `exp` must be within the range of `f64`
+/// including subnormals
+#[inline]
+fn pow2(exp: i64) -> f64 {
+    if exp >= -1022 {
+        f64::from_bits(((exp + 1023) as u64) << 52)
+    } else {
+        f64::from_bits(1 << (exp + 1074))
+    }
+}
+
+/// Round `mantissa * 2^exp` to the nearest `f64`, ties to even. `sticky`
+/// tells that nonzero bits below `mantissa` were dropped.
+fn round_binary(mantissa: u64, exp: i64, sticky: bool) -> f64 {
+    if mantissa == 0 {
+        return 0.0;
+    }
+    let bits = i64::from(64 - mantissa.leading_zeros());
+    let top = exp.saturating_add(bits - 1);
+    if top > 1023 {
+        return f64::INFINITY;
+    }
+    // subnormals have fewer significant bits
+    let precision = if top >= -1022 { 53 } else { 53 - (-1022 - top) };
+    let shift = bits - precision;
+    if shift <= 0 {
+        return mantissa as f64 * pow2(exp);
+    }
+    if shift > bits {
+        return 0.0;
+    }
+    let rest = if shift == 64 {
+        mantissa
+    } else {
+        mantissa & ((1 << shift) - 1)
+    };
+    let half = 1 << (shift - 1);
+    let mut kept = mantissa.checked_shr(shift as u32).unwrap_or(0);
+    if rest > half || (rest == half && (sticky || kept & 1 == 1)) {
+        kept += 1;
+    }
+    // rounding may carry into a new bit, which can only overflow
+    // the largest exponent
+    if exp + shift + i64::from(64 - kept.leading_zeros()) - 1 > 1023 {
+        return f64::INFINITY;
+    }
+    kept as f64 * pow2(exp + shift)
+}
+
+/// Value of hexadecimal floating constant like `0x1.8p3` from the hex
+/// digits of its mantissa (with optional point) and its binary exponent
+#[inline]
+fn hex_float_value(mantissa_text: &str, binary: i64) -> f64 {
+    let mut mantissa: u64 = 0;
+    let mut exp: i64 = 0;
+    let mut sticky = false;
+    let mut fraction = false;
+    for c in mantissa_text.chars() {
+        let digit = match c.to_digit(16) {
+            Some(digit) => u64::from(digit),
+            None => {
+                fraction = true;
+                continue;
             }
-            if *item == b'e' || *item == b'E' {
-                i_e = i;
-                break;
+        };
+        // keep as many digits as fit, only whether the rest are zero
+        // matters for rounding
+        if mantissa < 1 << 60 {
+            mantissa = mantissa << 4 | digit;
+            if fraction {
+                exp -= 4;
+            }
+        } else {
+            sticky |= digit != 0;
+            if !fraction {
+                exp += 4;
             }
         }
+    }
+    round_binary(mantissa, exp.saturating_add(binary), sticky)
+}
 
-    let (integer, decimal) = if i_point.is_some() {
-        let integer = unsafe {
-            str::from_utf8_unchecked(&input[*c_src - token_len as usize..i_point.unwrap()])
-        };
-        let integer = u32::from_str_radix(integer, 10)?;
-        let decimal = unsafe { str::from_utf8_unchecked(&input[i_point.unwrap() + 1..i_e]) };
-        (integer, u32::from_str_radix(decimal, 10)?)
+#[inline]
+pub fn parse_float(input: &[u8], c_src: &mut usize, token_len: u64) -> Result<Token, Error> {
+    let start = *c_src - token_len as usize;
+    let raw = ascii(&input[start..*c_src - 1]);
+    let text = without_separators(raw);
+    let at = |i| source_offset(raw, start, i);
+    let bytes = text.as_bytes();
+    let hex = text.starts_with("0x") || text.starts_with("0X");
+
+    // digits with a point, then exponent which is optional for decimal
+    // constants, the rest is suffix
+    let digits_start = if hex { 2 } else { 0 };
+    let mantissa_end = digits_start
+        + bytes[digits_start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit() || **b == b'.' || hex && b.is_ascii_hexdigit())
+            .count();
+    if hex && bytes[digits_start..mantissa_end].iter().all(|b| *b == b'.') {
+        return Err(Error::at(ErrorKind::MissingDigits, start));
+    }
+    let exponent: &[u8] = if hex { b"pP" } else { b"eE" };
+    let mut end = mantissa_end;
+    let mut binary = 0i64;
+    match bytes.get(end) {
+        Some(b) if exponent.contains(b) => {
+            let mut digits = end + 1;
+            let negative = bytes.get(digits) == Some(&b'-');
+            if let Some(b'+') | Some(b'-') = bytes.get(digits) {
+                digits += 1;
+            }
+            let len = bytes[digits..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            if len == 0 {
+                return Err(Error::at(ErrorKind::MissingDigits, at(end + 1)));
+            }
+            end = digits + len;
+            // the exponent can be arbitrary long, saturate instead of
+            // failing, the value is zero or infinity anyway
+            binary = bytes[digits..end].iter().fold(0i64, |acc, d| {
+                acc.saturating_mul(10).saturating_add(i64::from(d - b'0'))
+            });
+            if negative {
+                binary = -binary;
+            }
+        }
+        _ if hex => return Err(Error::at(ErrorKind::MissingExponent, at(end))),
+        _ => {}
+    }
+
+    let suffix = float_suffix(&text[end..]).ok_or_else(|| {
+        Error::at(
+            ErrorKind::InvalidSuffix(text[end..].to_string()),
+            at(end),
+        )
+    })?;
+    let value = if hex {
+        hex_float_value(&text[digits_start..mantissa_end], binary)
     } else {
-        let integer = unsafe { str::from_utf8_unchecked(&input[*c_src - token_len as usize..i_e]) };
-        let integer = u32::from_str_radix(integer, 10)?;
-        (integer, 0)
+        text[..end]
+            .parse::<f64>()
+            .map_err(|_| Error::at(ErrorKind::MissingDigits, start))?
     };
-
-    let exponent = unsafe { str::from_utf8_unchecked(&input[i_e + 1..*c_src - 1]) };
-    let exponent = i64::from_str_radix(exponent, 10).unwrap();
-    *c_src += 1;
-    Ok(Token::NumericLiteral(Number::new(
-        integer, decimal, exponent, 10,
-    )))
+    *c_src -= 1;
+    Ok(Token::NumericLiteral(Number::Float(Float::new(
+        raw.to_string(),
+        value,
+        suffix,
+    ))))
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::*;
+    use crate::{
+        error::{Error, ErrorKind},
+        options::{LexerOptions, Standard},
+        span::Position,
+    };
+    use ::internship::IStr;
+
+    fn int(value: u128, radix: u8) -> Integer {
+        Integer::new(value, radix, IntegerSuffix::default())
+    }
     should!(
         binary,
         "0b1 ",
-        vec![Token::NumericLiteral(Number::new(1, 0, 1, 2)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(1, 2))), Token::EOF]
     );
 
     should!(
         binary_capital,
         "0b1 ",
-        vec![Token::NumericLiteral(Number::new(1, 0, 1, 2)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(1, 2))), Token::EOF]
     );
 
     should!(
         binary_four,
         "0b110 ",
-        vec![Token::NumericLiteral(Number::new(6, 0, 1, 2)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(6, 2))), Token::EOF]
+    );
+
+    should!(
+        binary_gnu,
+        "0b1 ",
+        LexerOptions {
+            gnu: true,
+            ..LexerOptions::for_standard(Standard::C11)
+        },
+        vec![Token::NumericLiteral(Number::Integer(int(1, 2))), Token::EOF]
+    );
+
+    should_fail!(
+        binary_c11,
+        "0b1 ",
+        LexerOptions::for_standard(Standard::C11),
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("b1")),
+            Position::new(1, 1, 2)
+        )
     );
 
+    fn octal_prefix() -> LexerOptions {
+        LexerOptions {
+            octal_prefix: true,
+            ..LexerOptions::default()
+        }
+    }
+
     should!(
         octal,
         "0o7 ",
-        vec![Token::NumericLiteral(Number::new(7, 0, 1, 8)), Token::EOF]
+        octal_prefix(),
+        vec![Token::NumericLiteral(Number::Integer(int(7, 8))), Token::EOF]
     );
 
     should!(
         octal_cpaital,
         "0O7 ",
-        vec![Token::NumericLiteral(Number::new(7, 0, 1, 8)), Token::EOF]
+        octal_prefix(),
+        vec![Token::NumericLiteral(Number::Integer(int(7, 8))), Token::EOF]
     );
 
     should!(
         octal_eight,
         "0O110 ",
-        vec![Token::NumericLiteral(Number::new(72, 0, 1, 8)), Token::EOF]
+        octal_prefix(),
+        vec![Token::NumericLiteral(Number::Integer(int(72, 8))), Token::EOF]
+    );
+
+    should_fail!(
+        octal_prefix_disabled,
+        "0o7",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("o7")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should_fail!(
+        octal_prefix_digit,
+        "0o78",
+        octal_prefix(),
+        Error::new(ErrorKind::InvalidDigit { digit: '8', radix: 8 }, Position::new(3, 1, 4))
+    );
+
+    should!(
+        octal_leading_zero,
+        "017 00 0",
+        vec![
+            Token::NumericLiteral(Number::Integer(int(15, 8))),
+            Token::NumericLiteral(Number::Integer(int(0, 8))),
+            Token::NumericLiteral(Number::Integer(int(0, 10))),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        octal_suffix,
+        "0777ul",
+        vec![suffixed(511, 8, true, IntegerLength::Long), Token::EOF]
+    );
+
+    should_fail!(
+        octal_invalid_digit,
+        "x = 0129;",
+        Error::new(ErrorKind::InvalidDigit { digit: '9', radix: 8 }, Position::new(7, 1, 8))
+    );
+
+    should!(
+        octal_like_float,
+        "09.5 019e1",
+        vec![
+            float("09.5", 9.5, FloatSuffix::Plain),
+            float("019e1", 190.0, FloatSuffix::Plain),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        binary_disabled,
+        "0b1",
+        LexerOptions {
+            binary_literals: false,
+            ..LexerOptions::default()
+        },
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("b1")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should_fail!(
+        binary_invalid_digit,
+        "0b102",
+        Error::new(ErrorKind::InvalidDigit { digit: '2', radix: 2 }, Position::new(4, 1, 5))
     );
 
     should!(
         hex,
         "0xa ",
-        vec![Token::NumericLiteral(Number::new(10, 0, 1, 16)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(10, 16))), Token::EOF]
     );
 
     should!(
         hex_capital,
         "0Xa ",
-        vec![Token::NumericLiteral(Number::new(10, 0, 1, 16)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(10, 16))), Token::EOF]
     );
 
     should!(
         hex_sixteen,
         "0x10 ",
-        vec![Token::NumericLiteral(Number::new(16, 0, 1, 16)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(16, 16))), Token::EOF]
     );
 
     should!(
         decimal,
-        "01 ",
-        vec![Token::NumericLiteral(Number::new(1, 0, 1, 10)), Token::EOF]
+        "1 ",
+        vec![Token::NumericLiteral(Number::Integer(int(1, 10))), Token::EOF]
     );
 
     should!(
         decimal_ten,
         "10 ",
-        vec![Token::NumericLiteral(Number::new(10, 0, 1, 10)), Token::EOF]
+        vec![Token::NumericLiteral(Number::Integer(int(10, 10))), Token::EOF]
     );
 
+    fn float(raw: &str, value: f64, suffix: FloatSuffix) -> Token {
+        Token::NumericLiteral(Number::Float(Float::new(raw.to_string(), value, suffix)))
+    }
+
     should!(
         decimaldigits,
         "10.1 ",
-        vec![Token::NumericLiteral(Number::new(10, 1, 1, 10)), Token::EOF]
+        vec![float("10.1", 10.1, FloatSuffix::Plain), Token::EOF]
     );
 
     should!(
         decimaldigits_exponent_signed,
         "10.1e-2 ",
+        vec![float("10.1e-2", 0.101, FloatSuffix::Plain), Token::EOF]
+    );
+
+    should!(
+        decimal_exponent_signed,
+        "10e-2 ",
+        vec![float("10e-2", 0.1, FloatSuffix::Plain), Token::EOF]
+    );
+
+    should!(
+        decimal_exponent_signed_plus,
+        "10e+20 ",
+        vec![float("10e+20", 10e20, FloatSuffix::Plain), Token::EOF]
+    );
+
+    should!(
+        decimal_exponent_unsigneds,
+        "10e20 ",
+        vec![float("10e20", 10e20, FloatSuffix::Plain), Token::EOF]
+    );
+
+    should!(
+        fraction_leading_zero,
+        "1.05 1.5",
         vec![
-            Token::NumericLiteral(Number::new(10, 1, -2, 10)),
+            float("1.05", 1.05, FloatSuffix::Plain),
+            float("1.5", 1.5, FloatSuffix::Plain),
             Token::EOF
         ]
     );
 
     should!(
-        decimal_exponent_signed,
-        "10e-2 ",
+        fraction_only,
+        ".5;",
+        vec![float(".5", 0.5, FloatSuffix::Plain), Token::Semicolon, Token::EOF]
+    );
+
+    should!(
+        integer_part_only,
+        "5. + 1.e-3",
         vec![
-            Token::NumericLiteral(Number::new(10, 0, -2, 10)),
+            float("5.", 5.0, FloatSuffix::Plain),
+            Token::Plus,
+            float("1.e-3", 0.001, FloatSuffix::Plain),
             Token::EOF
         ]
     );
 
     should!(
-        decimal_exponent_signed_plus,
-        "10e+20 ",
+        zero_integer_part,
+        "0.5 0e1",
         vec![
-            Token::NumericLiteral(Number::new(10, 0, 20, 10)),
+            float("0.5", 0.5, FloatSuffix::Plain),
+            float("0e1", 0.0, FloatSuffix::Plain),
             Token::EOF
         ]
     );
 
     should!(
-        decimal_exponent_unsigneds,
-        "10e20 ",
+        suffix_float,
+        "1.5f 1e3F",
+        vec![
+            float("1.5f", 1.5, FloatSuffix::Float),
+            float("1e3F", 1000.0, FloatSuffix::Float),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        suffix_long_double,
+        "2.0L .1l",
+        vec![
+            float("2.0L", 2.0, FloatSuffix::Long),
+            float(".1l", 0.1, FloatSuffix::Long),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        member_after_dots,
+        "a.b..c",
+        vec![
+            Token::Identifier(IStr::new("a")),
+            Token::Dot,
+            Token::Identifier(IStr::new("b")),
+            Token::Dot,
+            Token::Dot,
+            Token::Identifier(IStr::new("c")),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        suffix_float_twice,
+        "1.0ff",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("ff")),
+            Position::new(3, 1, 4)
+        )
+    );
+
+    should_fail!(
+        suffix_unsigned_float,
+        "1.0u",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("u")),
+            Position::new(3, 1, 4)
+        )
+    );
+
+    should!(
+        hex_float,
+        "0x1.8p3 0X.8P+1 0x1p-2",
+        vec![
+            float("0x1.8p3", 12.0, FloatSuffix::Plain),
+            float("0X.8P+1", 1.0, FloatSuffix::Plain),
+            float("0x1p-2", 0.25, FloatSuffix::Plain),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        hex_float_suffix,
+        "0x1.fffffep+127f 0xAp0L",
         vec![
-            Token::NumericLiteral(Number::new(10, 0, 20, 10)),
+            float("0x1.fffffep+127f", f64::from(f32::MAX), FloatSuffix::Float),
+            float("0xAp0L", 10.0, FloatSuffix::Long),
             Token::EOF
         ]
     );
-}
\ No newline at end of file
+
+    fn value(input: &str) -> f64 {
+        match Lexer::lex(input).unwrap()[0] {
+            Token::NumericLiteral(Number::Float(ref f)) => f.value,
+            ref t => panic!("{:?} is not a float", t),
+        }
+    }
+
+    #[test]
+    fn hex_float_rounding() {
+        let ulp = f64::EPSILON;
+        // exactly half way rounds to even
+        assert_eq!(value("0x1.00000000000008p0"), 1.0);
+        assert_eq!(value("0x1.00000000000018p0"), 1.0 + 2.0 * ulp);
+        assert_eq!(value("0x1.fffffffffffff8p0"), 2.0);
+        // anything past half way rounds up, even far behind
+        assert_eq!(value("0x1.00000000000008000000001p0"), 1.0 + ulp);
+        assert_eq!(value("0x1.fffffffffffff7ffffffp0"), 2.0 - ulp);
+        assert_eq!(
+            value("0x123456789abcdef0123p-72"),
+            0x123_4567_89ab_cdef_0123_u128 as f64 / 2f64.powi(72)
+        );
+        // subnormals, underflow and overflow
+        assert_eq!(value("0x1p-1074"), f64::from_bits(1));
+        assert_eq!(value("0x1p-1075"), 0.0);
+        assert_eq!(value("0x3p-1076"), f64::from_bits(1));
+        assert_eq!(value("0x1.8p-1073"), f64::from_bits(3));
+        assert_eq!(value("0x1p-99999999999999999999"), 0.0);
+        assert_eq!(value("0x1.fffffffffffffp1023"), f64::MAX);
+        assert_eq!(value("0x1.fffffffffffff8p1023"), f64::INFINITY);
+        assert_eq!(value("0x1p1024"), f64::INFINITY);
+        assert_eq!(value("0x0p99999"), 0.0);
+    }
+
+    should_fail!(
+        hex_float_no_exponent,
+        "0x1.8;",
+        Error::new(ErrorKind::MissingExponent, Position::new(5, 1, 6))
+    );
+
+    should_fail!(
+        hex_float_exponent_digits,
+        "0x1p+f",
+        Error::new(ErrorKind::MissingDigits, Position::new(4, 1, 5))
+    );
+
+    should_fail!(
+        hex_float_no_digits,
+        "0x.p1",
+        Error::new(ErrorKind::MissingDigits, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        hex_float_suffix_invalid,
+        "0x1p1u",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("u")),
+            Position::new(5, 1, 6)
+        )
+    );
+
+    should_fail!(
+        decimal_overflow,
+        "x = 99999999999999999999999;",
+        Error::new(ErrorKind::IntegerOverflow, Position::new(4, 1, 5))
+    );
+
+    should_fail!(
+        exponent_missing,
+        "1e;",
+        Error::new(ErrorKind::MissingDigits, Position::new(2, 1, 3))
+    );
+
+    should_fail!(
+        exponent_sign_only,
+        "1e+",
+        Error::new(ErrorKind::MissingDigits, Position::new(2, 1, 3))
+    );
+
+    should_fail!(
+        hex_missing_digits,
+        "0x",
+        Error::new(ErrorKind::MissingDigits, Position::new(0, 1, 1))
+    );
+
+    fn suffixed(value: u128, radix: u8, unsigned: bool, length: IntegerLength) -> Token {
+        Token::NumericLiteral(Number::Integer(Integer::new(
+            value,
+            radix,
+            IntegerSuffix::new(unsigned, length),
+        )))
+    }
+
+    should!(
+        suffix_unsigned,
+        "10u",
+        vec![suffixed(10, 10, true, IntegerLength::Plain), Token::EOF]
+    );
+
+    should!(
+        suffix_long,
+        "10L ",
+        vec![suffixed(10, 10, false, IntegerLength::Long), Token::EOF]
+    );
+
+    should!(
+        suffix_unsigned_long,
+        "10Ul",
+        vec![suffixed(10, 10, true, IntegerLength::Long), Token::EOF]
+    );
+
+    should!(
+        suffix_long_long_unsigned,
+        "0x10llu;",
+        vec![
+            suffixed(16, 16, true, IntegerLength::LongLong),
+            Token::Semicolon,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        suffix_hex_digits,
+        "0xbULL",
+        vec![suffixed(11, 16, true, IntegerLength::LongLong), Token::EOF]
+    );
+
+    should_fail!(
+        suffix_mixed_case,
+        "10lL",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("lL")),
+            Position::new(2, 1, 3)
+        )
+    );
+
+    should_fail!(
+        suffix_twice_unsigned,
+        "10uLu",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("uLu")),
+            Position::new(2, 1, 3)
+        )
+    );
+
+    should_fail!(
+        suffix_unknown,
+        "1x2",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("x2")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should!(
+        max_unsigned,
+        "18446744073709551615u",
+        vec![suffixed(u128::from(u64::MAX), 10, true, IntegerLength::Plain), Token::EOF]
+    );
+
+    should_fail!(
+        max_unsigned_decimal,
+        "18446744073709551615",
+        Error::new(ErrorKind::IntegerOverflow, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        too_large,
+        "0x10000000000000000",
+        Error::new(ErrorKind::IntegerOverflow, Position::new(0, 1, 1))
+    );
+
+    fn c23() -> LexerOptions {
+        LexerOptions::for_standard(Standard::C23)
+    }
+
+    should!(
+        digit_separators,
+        "1'000 0xFF'ff 0b1'0 1'0.2'5e1'0",
+        c23(),
+        vec![
+            Token::NumericLiteral(Number::Integer(int(1000, 10))),
+            Token::NumericLiteral(Number::Integer(int(0xFFFF, 16))),
+            Token::NumericLiteral(Number::Integer(int(2, 2))),
+            Token::NumericLiteral(Number::Float(Float::new(
+                String::from("1'0.2'5e1'0"),
+                10.25e10,
+                FloatSuffix::Plain
+            ))),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        digit_separators_before_c23,
+        "1'2'",
+        vec![
+            Token::NumericLiteral(Number::Integer(int(1, 10))),
+            Token::CharLiteral(CharLiteral::new(
+                String::from("'2'"),
+                Encoding::Plain,
+                CodeUnits::U8(vec![b'2']),
+                50
+            )),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        digit_separator_position,
+        "0'7'8",
+        c23(),
+        Error::new(
+            ErrorKind::InvalidDigit {
+                digit: '8',
+                radix: 8
+            },
+            Position::new(4, 1, 5)
+        )
+    );
+
+    should!(
+        suffix_bit_precise,
+        "10wb 10uwb 0xfWBU",
+        c23(),
+        vec![
+            suffixed(10, 10, false, IntegerLength::BitPrecise),
+            suffixed(10, 10, true, IntegerLength::BitPrecise),
+            suffixed(15, 16, true, IntegerLength::BitPrecise),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        suffix_bit_precise_before_c23,
+        "10wb",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("wb")),
+            Position::new(2, 1, 3)
+        )
+    );
+
+    should!(
+        suffix_msvc,
+        "1i64 0xFFui64 2UI64",
+        LexerOptions {
+            msvc: true,
+            ..LexerOptions::default()
+        },
+        vec![
+            suffixed(1, 10, false, IntegerLength::LongLong),
+            suffixed(255, 16, true, IntegerLength::LongLong),
+            suffixed(2, 10, true, IntegerLength::LongLong),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        suffix_msvc_disabled,
+        "1ui64",
+        Error::new(
+            ErrorKind::InvalidSuffix(String::from("ui64")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    fn ty(input: &str, model: &DataModel) -> Option<IntegerType> {
+        match Lexer::lex(input).unwrap()[0] {
+            Token::NumericLiteral(Number::Integer(ref i)) => i.ty(model),
+            ref t => panic!("{:?} is not an integer", t),
+        }
+    }
+
+    #[test]
+    fn integer_type() {
+        let lp64 = &DataModel::LP64;
+        assert_eq!(ty("1", lp64), Some(IntegerType::Int));
+        assert_eq!(ty("2147483647", lp64), Some(IntegerType::Int));
+        assert_eq!(ty("2147483648", lp64), Some(IntegerType::Long));
+        assert_eq!(ty("2147483648", &DataModel::LLP64), Some(IntegerType::LongLong));
+        assert_eq!(ty("0x80000000", lp64), Some(IntegerType::UnsignedInt));
+        assert_eq!(ty("4294967295u", lp64), Some(IntegerType::UnsignedInt));
+        assert_eq!(ty("4294967296u", lp64), Some(IntegerType::UnsignedLong));
+        assert_eq!(ty("1l", lp64), Some(IntegerType::Long));
+        assert_eq!(ty("1ll", lp64), Some(IntegerType::LongLong));
+        assert_eq!(ty("0x8000000000000000l", lp64), Some(IntegerType::UnsignedLong));
+        assert_eq!(
+            ty("0x8000000000000000", &DataModel::ILP32),
+            Some(IntegerType::UnsignedLongLong)
+        );
+        let ll = IntegerSuffix::new(false, IntegerLength::LongLong);
+        assert_eq!(Integer::new(1 << 63, 10, ll).ty(lp64), None);
+        let wb = IntegerSuffix::new(false, IntegerLength::BitPrecise);
+        let uwb = IntegerSuffix::new(true, IntegerLength::BitPrecise);
+        assert_eq!(Integer::new(0, 10, wb).ty(lp64), Some(IntegerType::BitInt(2)));
+        assert_eq!(Integer::new(255, 10, uwb).ty(lp64), Some(IntegerType::UnsignedBitInt(8)));
+        assert_eq!(
+            Integer::new(u128::MAX, 16, wb).ty(lp64),
+            Some(IntegerType::BitInt(129))
+        );
+        assert_eq!(IntegerType::BitInt(129).max_value(lp64), u128::MAX);
+    }
+}
@@ -0,0 +1,111 @@
+/// Options selecting the language standard and which non-standard
+/// literal forms are accepted
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct LexerOptions {
+    /// Standard whose keywords and literal forms are lexed
+    pub standard: Standard,
+    /// GNU extensions, keywords reserved by later standards like
+    /// `_Bool` or `_Static_assert` and `inline` are keywords in all
+    /// standards
+    pub gnu: bool,
+    /// MSVC extensions, keywords like `__declspec` and `__int64` and
+    /// `i64` integer suffixes
+    pub msvc: bool,
+    /// Accept digraphs like `<:`, which are part of C since C95
+    pub digraphs: bool,
+    /// Accept binary integer constants like `0b101`, a GNU extension
+    /// standardized in C23. They are accepted in GNU mode and since C23
+    /// regardless of this option.
+    pub binary_literals: bool,
+    /// Accept octal integer constants with `0o` prefix like `0o17`, which
+    /// are not part of any C standard
+    pub octal_prefix: bool,
+    /// `wchar_t` is 16 bits wide and `L` literals are encoded in UTF-16
+    /// like on Windows, otherwise it is 32 bits wide and UTF-32 is used
+    pub short_wchar: bool,
+    /// Plain `char` is signed, which makes `'\377'` equal to `-1`
+    pub signed_char: bool,
+    /// How plain character constants with more than one character
+    /// like `'ab'` are treated
+    pub multichar: MultiChar,
+    /// Replace trigraphs like `??=` before lexing, removed in C23 and
+    /// disabled by default in modern compilers. Trigraphs are warned
+    /// about either way.
+    pub trigraphs: bool,
+    /// Handle `#line` directives and GNU linemarkers like
+    /// `# 42 "foo.c" 2` found in preprocessed output. They are not
+    /// returned as tokens, instead they change the line numbers and
+    /// file name reported for the tokens after them.
+    pub line_markers: bool,
+}
+
+/// Version of the C standard
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Clone, Copy, Hash)]
+pub enum Standard {
+    /// ANSI C, ISO/IEC 9899:1990
+    C89,
+    /// ISO/IEC 9899:1990/AMD1:1995, adds digraphs
+    C95,
+    /// ISO/IEC 9899:1999, adds `inline`, `restrict`, `_Bool`, `_Complex`,
+    /// `_Imaginary` and `__func__`
+    C99,
+    /// ISO/IEC 9899:2011, adds `_Alignas`, `_Alignof`, `_Atomic`,
+    /// `_Generic`, `_Noreturn`, `_Static_assert`, `_Thread_local` and
+    /// `u`, `U` and `u8` encoding prefixes
+    C11,
+    /// ISO/IEC 9899:2018, the same language as C11
+    C17,
+    /// ISO/IEC 9899:2024
+    C23,
+}
+
+/// Treatment of plain character constants with more than one character,
+/// their value is implementation-defined
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum MultiChar {
+    /// Shift characters into `int` from the left like GCC, Clang and
+    /// MSVC do, so `'ab'` is `0x6162`. A warning is reported.
+    #[default]
+    Pack,
+    /// Report an error
+    Reject,
+}
+
+impl LexerOptions {
+    /// Options for strictly conforming standard `standard` without
+    /// extensions. Trigraphs are replaced before C23 and binary
+    /// literals are accepted from C23 on.
+    pub fn for_standard(standard: Standard) -> Self {
+        Self {
+            standard,
+            gnu: false,
+            msvc: false,
+            digraphs: standard >= Standard::C95,
+            binary_literals: standard >= Standard::C23,
+            trigraphs: standard < Standard::C23,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for LexerOptions {
+    /// Permissive C17 preset kept for compatibility, unlike
+    /// `for_standard(Standard::C17)` it accepts binary constants, which
+    /// are a GNU and C23 extension, and doesn't replace trigraphs. Plain
+    /// `char` is signed and GNU and MSVC keywords are not recognised.
+    fn default() -> Self {
+        Self {
+            standard: Standard::C17,
+            gnu: false,
+            msvc: false,
+            digraphs: true,
+            binary_literals: true,
+            octal_prefix: false,
+            short_wchar: false,
+            signed_char: true,
+            multichar: MultiChar::Pack,
+            trigraphs: false,
+            line_markers: false,
+        }
+    }
+}
@@ -0,0 +1,1601 @@
+//! C preprocessor, translation phase 4.
+//!
+//! [`Preprocessor`] runs on tokens of this crate and implements
+//! `#define` and `#undef` with object-like, function-like and variadic
+//! macros including `#` and `##`, `#include` through a [`Resolver`],
+//! conditional inclusion with `#if`, `#ifdef`, `#ifndef`, `#elif`,
+//! `#else` and `#endif`, and `#error`, `#warning`, `#line` and
+//! `#pragma`. Macros are expanded following the rescanning rules of
+//! C11 6.10.3.4, a macro is never expanded again inside its own
+//! expansion.
+//!
+//! Output tokens remember the macro invocations they were expanded
+//! from. Line terminators are dropped from the output.
+
+use crate::{
+    conditional::{self, Conditionals, DirectiveToken},
+    error::{Error, ErrorKind, Warning},
+    identifier::keyword_spelling,
+    options::{LexerOptions, Standard},
+    source_map,
+    span::{Position, Span},
+    state_machine::Tokenizer,
+    token::{HeaderName, Token},
+};
+use internship::IStr;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Files including each other deeper than this are an error, it stops
+// infinite recursion of files without include guards
+const MAX_INCLUDE_DEPTH: usize = 200;
+
+/// File found by [`Resolver`]
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct IncludeFile {
+    /// Name of the file, included files are reported by it and it is
+    /// what `__FILE__` expands to
+    pub name: String,
+    /// Contents of the file
+    pub contents: String,
+}
+
+impl IncludeFile {
+    /// Create new include file
+    #[inline]
+    pub fn new(name: String, contents: String) -> Self {
+        Self { name, contents }
+    }
+}
+
+/// Finds files included by `#include`
+pub trait Resolver {
+    /// Find file `name` included from file `from`, `system` is set for
+    /// names in angle brackets like `<stdio.h>`
+    fn resolve(&mut self, name: &str, system: bool, from: &str) -> Option<IncludeFile>;
+}
+
+/// In-memory files by their names, both quoted and angle-bracket names
+/// are looked up as is
+impl Resolver for HashMap<String, String> {
+    fn resolve(&mut self, name: &str, _system: bool, _from: &str) -> Option<IncludeFile> {
+        let contents = self.get(name)?;
+        Some(IncludeFile::new(name.to_string(), contents.clone()))
+    }
+}
+
+/// Resolver reading files from disk
+///
+/// Quoted names are looked up in the directory of the including file
+/// first, then like angle-bracket names in the include directories in
+/// order.
+#[derive(Debug, Clone, Default)]
+pub struct FileSystem {
+    /// Directories searched for included files
+    pub include_dirs: Vec<PathBuf>,
+}
+
+impl FileSystem {
+    /// Create new resolver searching `include_dirs`
+    #[inline]
+    pub fn new(include_dirs: Vec<PathBuf>) -> Self {
+        Self { include_dirs }
+    }
+}
+
+impl Resolver for FileSystem {
+    fn resolve(&mut self, name: &str, system: bool, from: &str) -> Option<IncludeFile> {
+        let local = Path::new(from).parent().filter(|_| !system);
+        local
+            .into_iter()
+            .chain(self.include_dirs.iter().map(PathBuf::as_path))
+            .map(|dir| dir.join(name))
+            .find_map(|path| {
+                let contents = fs::read_to_string(&path).ok()?;
+                Some(IncludeFile::new(
+                    path.to_string_lossy().into_owned(),
+                    contents,
+                ))
+            })
+    }
+}
+
+/// Macro invocation a token was expanded from
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct Expansion {
+    /// Name of the macro
+    pub name: String,
+    /// Location of the macro name where it is invoked
+    pub span: Span,
+    /// Index of the file of `span` in [`Preprocessor::files`]
+    pub file: usize,
+}
+
+/// Token after preprocessing together with where it came from
+#[derive(PartialEq, Debug, Clone)]
+pub struct ExpandedToken {
+    /// Preprocessed token
+    pub token: Token,
+    /// Location where the token is written, in the macro definition for
+    /// tokens from replacement lists. Tokens made by `#` and `##` are
+    /// located at the operator.
+    pub span: Span,
+    /// Index of the file of `span` in [`Preprocessor::files`]
+    pub file: usize,
+    /// Macro invocations the token went through, the last one first,
+    /// empty for tokens written directly in the source
+    pub expansions: Vec<Expansion>,
+}
+
+/// `#pragma` directive other than `#pragma once`, which is handled by
+/// the preprocessor
+#[derive(PartialEq, Debug, Clone)]
+pub struct Pragma {
+    /// Tokens after `pragma`, they are not macro expanded
+    pub tokens: Vec<Token>,
+    /// Location of the `#` of the directive
+    pub span: Span,
+    /// Index of the file of `span` in [`Preprocessor::files`]
+    pub file: usize,
+}
+
+// Token as it goes through macro expansion
+#[derive(Debug, Clone)]
+struct PpToken {
+    token: Token,
+    // spelling, used by `#` and `##`
+    text: String,
+    span: Span,
+    file: usize,
+    // preceded by white space
+    space: bool,
+    // first token on its line in the source, only such `#` starts a
+    // directive
+    line_start: bool,
+    // macros that must not be expanded from this token any more
+    hidden: Vec<IStr>,
+    expansions: Vec<Expansion>,
+}
+
+impl PpToken {
+    #[inline]
+    fn is_hidden(&self, name: &str) -> bool {
+        self.hidden.iter().any(|hidden| &**hidden == name)
+    }
+}
+
+impl DirectiveToken for PpToken {
+    #[inline]
+    fn token(&self) -> &Token {
+        &self.token
+    }
+
+    #[inline]
+    fn span(&self) -> Span {
+        self.span
+    }
+
+    #[inline]
+    fn line_start(&self) -> bool {
+        self.line_start
+    }
+
+    #[inline]
+    fn name(&self) -> Option<&str> {
+        match &self.token {
+            Token::Identifier(name)
+            | Token::TypedefName(name)
+            | Token::EnumerationConstant(name) => Some(name),
+            // keywords may have several spellings, the one written is
+            // the name
+            _ if keyword_spelling(&self.text).is_some() => Some(&self.text),
+            _ => None,
+        }
+    }
+}
+
+impl From<PpToken> for ExpandedToken {
+    fn from(token: PpToken) -> Self {
+        Self {
+            token: token.token,
+            span: token.span,
+            file: token.file,
+            expansions: token.expansions,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Macro {
+    // `None` for object-like macros, variadic ones have `__VA_ARGS__`
+    // as the last parameter
+    params: Option<Vec<IStr>>,
+    variadic: bool,
+    body: Vec<PpToken>,
+}
+
+impl Macro {
+    #[inline]
+    fn param(&self, token: &PpToken) -> Option<usize> {
+        let name = token.name()?;
+        self.params.as_ref()?.iter().position(|p| &**p == name)
+    }
+
+    // Definitions are the same if they have the same parameters and
+    // replacement lists are spelled the same with white space at the
+    // same places
+    #[inline]
+    fn same(&self, other: &Macro) -> bool {
+        self.params == other.params
+            && self.body.len() == other.body.len()
+            && self
+                .body
+                .iter()
+                .zip(&other.body)
+                .enumerate()
+                .all(|(i, (a, b))| a.text == b.text && (i == 0 || a.space == b.space))
+    }
+}
+
+// Tokens of a file, or of a directive line when there is no tokenizer
+struct Input<'s> {
+    tokenizer: Option<Tokenizer<'s>>,
+    text: &'s str,
+    trigraphs: bool,
+    // index of the file being read
+    origin: usize,
+    // index of the file tokens are attributed to, changed by `#line`
+    file: usize,
+    // presumed line number set by `#line` minus the physical one
+    line_delta: isize,
+    // end offset of the last lexed token
+    prev_end: usize,
+    // tokens pushed back or produced by macro expansion, they are read
+    // before the tokenizer
+    pending: VecDeque<PpToken>,
+}
+
+impl<'s> Input<'s> {
+    #[inline]
+    fn file(text: &'s str, file: usize, options: LexerOptions) -> Self {
+        Self {
+            // `#line` is handled here, not by the tokenizer
+            tokenizer: Some(Tokenizer::with_options(
+                text,
+                LexerOptions {
+                    line_markers: false,
+                    ..options
+                },
+            )),
+            text,
+            trigraphs: options.trigraphs,
+            origin: file,
+            file,
+            line_delta: 0,
+            prev_end: 0,
+            pending: VecDeque::new(),
+        }
+    }
+
+    #[inline]
+    fn tokens(tokens: Vec<PpToken>) -> Self {
+        Self {
+            tokenizer: None,
+            text: "",
+            trigraphs: false,
+            origin: 0,
+            file: 0,
+            line_delta: 0,
+            prev_end: 0,
+            pending: tokens.into(),
+        }
+    }
+
+    // Next token, `None` at the end of input
+    fn next(&mut self) -> Result<Option<PpToken>, Error> {
+        if let Some(token) = self.pending.pop_front() {
+            return Ok(Some(token));
+        }
+        let tokenizer = match &mut self.tokenizer {
+            Some(tokenizer) => tokenizer,
+            None => return Ok(None),
+        };
+        let token = match tokenizer.next_token()? {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        let (start, end) = (token.span.start.offset, token.span.end.offset);
+        let text = source_map::translate(&self.text.as_bytes()[start..end], self.trigraphs).0;
+        let mut span = token.span;
+        for position in &mut [&mut span.start, &mut span.end] {
+            position.line = (position.line as isize + self.line_delta) as usize;
+        }
+        let space = token.line_start || start > self.prev_end;
+        self.prev_end = end;
+        Ok(Some(PpToken {
+            token: token.token,
+            text: String::from_utf8_lossy(&text).into_owned(),
+            span,
+            file: self.file,
+            space,
+            line_start: token.line_start,
+            hidden: Vec::new(),
+            expansions: Vec::new(),
+        }))
+    }
+
+    // Next token of a group that is skipped, lines that can't be lexed
+    // are skipped too
+    fn next_skipped(&mut self) -> Option<PpToken> {
+        loop {
+            match self.next() {
+                Ok(token) => return token,
+                Err(_) => self.tokenizer.as_mut()?.skip_line(),
+            }
+        }
+    }
+
+    // Skip the rest of the line, which may not be lexed correctly
+    fn skip_line(&mut self) {
+        while let Some(token) = self.next_skipped() {
+            if token.token == Token::LineTerminator {
+                break;
+            }
+        }
+    }
+
+    // Skip a group up to `#elif`, `#else` or `#endif` of the current
+    // conditional, which is left to be read next
+    fn skip_group(&mut self) {
+        if let Some((hash, name)) = conditional::skip_group(|| self.next_skipped()) {
+            self.pending.push_front(name);
+            self.pending.push_front(hash);
+        }
+    }
+
+    // Skip to `(` of function-like macro invocation, tokens are put
+    // back if there is none
+    fn lparen(&mut self) -> Result<bool, Error> {
+        let mut skipped = Vec::new();
+        while let Some(next) = self.next()? {
+            match next.token {
+                Token::LineTerminator => skipped.push(next),
+                Token::LParen => return Ok(true),
+                _ => {
+                    skipped.push(next);
+                    break;
+                }
+            }
+        }
+        for t in skipped.into_iter().rev() {
+            self.pending.push_front(t);
+        }
+        Ok(false)
+    }
+
+    // Rest of the directive line
+    fn line(&mut self) -> Result<Vec<PpToken>, Error> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next()? {
+            if token.token == Token::LineTerminator {
+                break;
+            }
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    // Physical line number of the next line
+    #[inline]
+    fn next_line(&self) -> usize {
+        self.tokenizer.as_ref().map_or(1, |t| t.position().line)
+    }
+}
+
+/// C preprocessor
+///
+/// Files are preprocessed with [`Preprocessor::preprocess`], macros
+/// defined in them stay defined for following files. `__FILE__`,
+/// `__LINE__`, `__STDC__`, `__STDC_HOSTED__` and `__STDC_VERSION__` of
+/// the selected standard are predefined, `__DATE__` and `__TIME__` are
+/// left to the caller so the output is reproducible.
+#[derive(Debug, Clone)]
+pub struct Preprocessor<R> {
+    resolver: R,
+    options: LexerOptions,
+    macros: HashMap<String, Macro>,
+    files: Vec<String>,
+    // files with `#pragma once`
+    once: HashSet<String>,
+    depth: usize,
+    output: Vec<ExpandedToken>,
+    pragmas: Vec<Pragma>,
+    warnings: Vec<Warning>,
+}
+
+impl<R: Resolver> Preprocessor<R> {
+    /// Create preprocessor finding included files with `resolver`
+    pub fn new(resolver: R) -> Self {
+        Self::with_options(resolver, LexerOptions::default())
+    }
+
+    /// Create preprocessor lexing files with `options`
+    pub fn with_options(resolver: R, options: LexerOptions) -> Self {
+        let mut preprocessor = Self {
+            resolver,
+            options,
+            macros: HashMap::new(),
+            files: Vec::new(),
+            once: HashSet::new(),
+            depth: 0,
+            output: Vec::new(),
+            pragmas: Vec::new(),
+            warnings: Vec::new(),
+        };
+        let version = match options.standard {
+            Standard::C89 => None,
+            Standard::C95 => Some("__STDC_VERSION__ 199409L"),
+            Standard::C99 => Some("__STDC_VERSION__ 199901L"),
+            Standard::C11 => Some("__STDC_VERSION__ 201112L"),
+            Standard::C17 => Some("__STDC_VERSION__ 201710L"),
+            Standard::C23 => Some("__STDC_VERSION__ 202311L"),
+        };
+        let predefined = ["__STDC__ 1", "__STDC_HOSTED__ 1"];
+        for definition in predefined.iter().chain(&version) {
+            preprocessor
+                .define(definition)
+                .expect("predefined macros are valid");
+        }
+        preprocessor
+    }
+
+    /// Define macro like `#define` does, `definition` is the rest of the
+    /// directive like `"DEBUG 1"` or `"MAX(a, b) ((a) > (b) ? (a) : (b))"`
+    pub fn define(&mut self, definition: &str) -> Result<(), Error> {
+        let file = self.add_file("<command line>");
+        let mut input = Input::file(definition, file, self.options);
+        let tokens = input.line()?;
+        self.define_macro(tokens, Position::default())
+    }
+
+    /// Remove definition of macro, like `#undef` does
+    pub fn undefine(&mut self, name: &str) {
+        self.macros.remove(name);
+    }
+
+    /// Whether macro is defined
+    pub fn is_defined(&self, name: &str) -> bool {
+        self.macros.contains_key(name) || is_builtin(name)
+    }
+
+    /// Preprocess file `name` with contents `source`, the output ends
+    /// with [`Token::EOF`]
+    pub fn preprocess(&mut self, name: &str, source: &str) -> Result<Vec<ExpandedToken>, Error> {
+        let file = self.add_file(name);
+        let end = self.file(file, source);
+        let mut output = std::mem::take(&mut self.output);
+        let end = end?;
+        output.push(ExpandedToken {
+            token: Token::EOF,
+            span: Span::new(end, end),
+            file,
+            expansions: Vec::new(),
+        });
+        Ok(output)
+    }
+
+    /// Names of files tokens come from, [`ExpandedToken::file`] is an
+    /// index into them. Names given by `#line` are included.
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Pragmas seen so far
+    pub fn pragmas(&self) -> &[Pragma] {
+        &self.pragmas
+    }
+
+    /// Warnings about redefined macros and from `#warning`, positions
+    /// are in the file the directive is in
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    fn add_file(&mut self, name: &str) -> usize {
+        match self.files.iter().position(|file| file == name) {
+            Some(index) => index,
+            None => {
+                self.files.push(name.to_string());
+                self.files.len() - 1
+            }
+        }
+    }
+
+    // Preprocess file and return its end position
+    fn file(&mut self, file: usize, source: &str) -> Result<Position, Error> {
+        let mut input = Input::file(source, file, self.options);
+        let mut conditionals = Conditionals::default();
+        while let Some(token) = input.next()? {
+            if token.line_start && token.token == Token::Hash {
+                // included files are preprocessed here so only frames of
+                // this function pile up for nested includes
+                if let Some((file, at)) = self.directive(&mut input, token, &mut conditionals)? {
+                    self.included(file, at)?;
+                }
+            } else if token.token != Token::LineTerminator && !self.expand(&mut input, &token)? {
+                self.output.push(token.into());
+            }
+        }
+        conditionals.end()?;
+        Ok(input
+            .tokenizer
+            .map_or_else(Position::default, |t| t.position()))
+    }
+
+    // Process directive, returns file to include for `#include`
+    fn directive(
+        &mut self,
+        input: &mut Input,
+        hash: PpToken,
+        conditionals: &mut Conditionals,
+    ) -> Result<Option<(IncludeFile, Position)>, Error> {
+        let at = hash.span.start;
+        let name = match input.next()? {
+            Some(token) if token.token == Token::LineTerminator => return Ok(None),
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        if let Some("include") | Some("include_next") | Some("import") = name.name() {
+            let tokens = input.line()?;
+            return Ok(self.include(input, tokens, at)?.map(|file| (file, at)));
+        }
+        // lines of conditional directives whose condition isn't evaluated
+        // don't have to be lexed correctly
+        let mut tokens = match name.name() {
+            Some(directive)
+                if conditional::is_conditional(directive) && !conditionals.evaluates(directive) =>
+            {
+                input.skip_line();
+                Vec::new()
+            }
+            _ => input.line()?,
+        };
+        let directive = match name.name() {
+            Some(directive) => directive,
+            // GNU linemarker `# 42 "file.c" 2` works like `#line`
+            None if matches!(name.token, Token::NumericLiteral(_)) => {
+                tokens.insert(0, name);
+                self.line(input, tokens, at, true)?;
+                return Ok(None);
+            }
+            None => return Err(Error::new(ErrorKind::InvalidDirective(name.text), at)),
+        };
+        let result = match directive {
+            "define" => self.define_macro(tokens, name.span.end),
+            "undef" => {
+                let macro_name = tokens
+                    .first()
+                    .and_then(PpToken::name)
+                    .ok_or_else(|| Error::new(ErrorKind::MissingMacroName, name.span.end))?;
+                self.macros.remove(macro_name);
+                Ok(())
+            }
+            "if" | "ifdef" | "ifndef" | "elif" | "elifdef" | "elifndef" | "else" | "endif" => {
+                let end = name.span.end;
+                let take = conditionals.directive(directive, at, || {
+                    self.condition(directive, tokens, end)
+                })?;
+                if !take {
+                    input.skip_group();
+                }
+                Ok(())
+            }
+            "line" => self.line(input, tokens, at, false),
+            "error" => Err(Error::new(ErrorKind::ErrorDirective(spelling(&tokens)), at)),
+            "warning" => {
+                let kind = ErrorKind::WarningDirective(spelling(&tokens));
+                self.warnings.push(Warning::new(kind, at));
+                Ok(())
+            }
+            "pragma" => {
+                if tokens.len() == 1 && tokens[0].name() == Some("once") {
+                    self.once.insert(self.files[input.origin].clone());
+                } else {
+                    self.pragmas.push(Pragma {
+                        tokens: tokens.into_iter().map(|t| t.token).collect(),
+                        span: hash.span,
+                        file: hash.file,
+                    });
+                }
+                Ok(())
+            }
+            _ => Err(Error::new(
+                ErrorKind::InvalidDirective(directive.to_string()),
+                at,
+            )),
+        };
+        result.map(|_| None)
+    }
+
+    fn define_macro(&mut self, tokens: Vec<PpToken>, at: Position) -> Result<(), Error> {
+        let mut tokens = tokens.into_iter().peekable();
+        let name = tokens
+            .next()
+            .filter(|t| t.name().is_some())
+            .ok_or_else(|| Error::new(ErrorKind::MissingMacroName, at))?;
+        let mut params = None;
+        let mut variadic = false;
+        // function-like macro has `(` right after its name
+        if tokens
+            .peek()
+            .is_some_and(|t| t.token == Token::LParen && !t.space)
+        {
+            let lparen = tokens.next().expect("peeked");
+            let invalid = || Error::new(ErrorKind::InvalidMacroParameters, lparen.span.start);
+            let mut names: Vec<IStr> = Vec::new();
+            loop {
+                let token = tokens.next().ok_or_else(invalid)?;
+                match token.token {
+                    Token::RParen if names.is_empty() => break,
+                    Token::ELLIPSIS => {
+                        variadic = true;
+                        names.push(IStr::new("__VA_ARGS__"));
+                    }
+                    _ => match token.name() {
+                        Some(param) if !names.iter().any(|n| &**n == param) => {
+                            names.push(IStr::new(param))
+                        }
+                        _ => return Err(invalid()),
+                    },
+                }
+                match tokens.next().map(|t| t.token) {
+                    Some(Token::RParen) => break,
+                    Some(Token::Comma) if !variadic => {}
+                    _ => return Err(invalid()),
+                }
+            }
+            params = Some(names);
+        }
+        let mut body: Vec<PpToken> = tokens.collect();
+        for token in &mut body {
+            token.line_start = false;
+        }
+        let definition = Macro {
+            params,
+            variadic,
+            body,
+        };
+        check_body(&definition)?;
+        let macro_name = name.name().expect("checked above").to_string();
+        if let Some(previous) = self.macros.get(&macro_name) {
+            if !previous.same(&definition) {
+                let kind = ErrorKind::MacroRedefined(macro_name.clone());
+                self.warnings.push(Warning::new(kind, name.span.start));
+            }
+        }
+        self.macros.insert(macro_name, definition);
+        Ok(())
+    }
+
+    // Find file included by `#include`, `None` if it has `#pragma once`
+    // and is already included
+    fn include(
+        &mut self,
+        input: &mut Input,
+        tokens: Vec<PpToken>,
+        at: Position,
+    ) -> Result<Option<IncludeFile>, Error> {
+        let invalid = || Error::new(ErrorKind::InvalidInclude, at);
+        let first = tokens.first().ok_or_else(invalid)?;
+        let position = first.span.start;
+        let (name, system) = match &first.token {
+            Token::HeaderName(HeaderName { name, system }) => (name.clone(), *system),
+            // computed include, the macro expanded line has to be a
+            // header name
+            _ => {
+                let tokens = self.expand_all(tokens)?;
+                match tokens.first().map(|t| &t.token) {
+                    Some(Token::StringLiteral(literal)) if tokens.len() == 1 => {
+                        let name = &literal.raw[1..literal.raw.len() - 1];
+                        (name.to_string(), false)
+                    }
+                    Some(Token::Lt) if tokens.last().map(|t| &t.token) == Some(&Token::Gt) => {
+                        let name = &tokens[1..tokens.len() - 1];
+                        (spelling(name), true)
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+        };
+        if self.depth >= MAX_INCLUDE_DEPTH {
+            return Err(Error::new(ErrorKind::IncludeDepth, at));
+        }
+        let from = self.files[input.origin].clone();
+        let file = self
+            .resolver
+            .resolve(&name, system, &from)
+            .ok_or_else(|| Error::new(ErrorKind::IncludeNotFound(name), position))?;
+        if self.once.contains(&file.name) {
+            return Ok(None);
+        }
+        Ok(Some(file))
+    }
+
+    // Preprocess included file, errors in it are reported at `at`
+    fn included(&mut self, file: IncludeFile, at: Position) -> Result<(), Error> {
+        let index = self.add_file(&file.name);
+        self.depth += 1;
+        let result = self.file(index, &file.contents);
+        self.depth -= 1;
+        result
+            .map(|_| ())
+            .map_err(|e| Error::new(ErrorKind::InInclude(file.name, Box::new(e)), at))
+    }
+
+    // Evaluate condition of `#if` or `#elif` family directive
+    fn condition(
+        &mut self,
+        directive: &str,
+        tokens: Vec<PpToken>,
+        at: Position,
+    ) -> Result<bool, Error> {
+        let end = tokens.last().map_or(at, |t| t.span.end);
+        let tokens = match directive {
+            "if" | "elif" => self.expand_condition(tokens)?,
+            _ => tokens,
+        };
+        conditional::condition(directive, &tokens, at, end, |name| self.is_defined(name))
+    }
+
+    // Expand macros in condition except operands of `defined`
+    fn expand_condition(&mut self, tokens: Vec<PpToken>) -> Result<Vec<PpToken>, Error> {
+        let mut input = Input::tokens(tokens);
+        let mut output = Vec::new();
+        while let Some(token) = input.next()? {
+            if token.name() == Some("defined") {
+                output.push(token);
+                let mut operand = input.next()?;
+                if let Some(lparen) = operand.take_if(|t| t.token == Token::LParen) {
+                    output.push(lparen);
+                    output.extend(input.next()?);
+                    operand = input.next()?;
+                }
+                output.extend(operand);
+            } else if !self.expand(&mut input, &token)? {
+                output.push(token);
+            }
+        }
+        Ok(output)
+    }
+
+    // `#line` or linemarker, which may have flags after the file name
+    fn line(
+        &mut self,
+        input: &mut Input,
+        tokens: Vec<PpToken>,
+        at: Position,
+        linemarker: bool,
+    ) -> Result<(), Error> {
+        let invalid = || Error::new(ErrorKind::InvalidLineDirective, at);
+        let tokens = self.expand_all(tokens)?;
+        if tokens.len() > 2 && !linemarker {
+            return Err(invalid());
+        }
+        let line = match tokens.first() {
+            Some(PpToken {
+                token: Token::NumericLiteral(_),
+                text,
+                ..
+            }) if text.bytes().all(|c| c.is_ascii_digit()) => {
+                text.parse::<usize>().map_err(|_| invalid())?
+            }
+            _ => return Err(invalid()),
+        };
+        match tokens.get(1).map(|t| &t.token) {
+            Some(Token::StringLiteral(literal)) => {
+                let name = literal.text().ok_or_else(invalid)?;
+                input.file = self.add_file(&name);
+            }
+            Some(_) => return Err(invalid()),
+            None => {}
+        }
+        input.line_delta = line as isize - input.next_line() as isize;
+        Ok(())
+    }
+
+    // Expand macros in tokens of a directive line
+    fn expand_all(&mut self, tokens: Vec<PpToken>) -> Result<Vec<PpToken>, Error> {
+        let mut input = Input::tokens(tokens);
+        let mut output = Vec::new();
+        while let Some(token) = input.next()? {
+            if !self.expand(&mut input, &token)? {
+                output.push(token);
+            }
+        }
+        Ok(output)
+    }
+
+    // Expand macro invoked by `token` if it is one, its expansion is
+    // put back to `input` to be rescanned
+    fn expand(&mut self, input: &mut Input, token: &PpToken) -> Result<bool, Error> {
+        let name = match token.name() {
+            Some(name) if !token.is_hidden(name) => name,
+            _ => return Ok(false),
+        };
+        let definition = match self.macros.get(name) {
+            Some(definition) => definition.clone(),
+            None => return self.builtin(input, token),
+        };
+        let mut hidden = token.hidden.clone();
+        let args = match &definition.params {
+            None => Vec::new(),
+            Some(params) => {
+                if !input.lparen()? {
+                    return Ok(false);
+                }
+                let (args, rparen) = arguments(input, token, params.len(), definition.variadic)?;
+                // C11 6.10.3.4 doesn't say whether a macro can be expanded
+                // again from tokens after it, only macros hidden in both
+                // the name and `)` stay hidden like in Prosser's algorithm
+                hidden.retain(|h| rparen.hidden.contains(h));
+                args
+            }
+        };
+        hidden.push(IStr::new(name));
+        let mut body = self.substitute(&definition, args, token)?;
+        let expansion = Expansion {
+            name: name.to_string(),
+            span: token.span,
+            file: token.file,
+        };
+        for t in &mut body {
+            t.hidden.extend(hidden.iter().cloned());
+            t.expansions.insert(0, expansion.clone());
+            t.line_start = false;
+        }
+        if let Some(first) = body.first_mut() {
+            first.space = token.space;
+        }
+        for t in body.into_iter().rev() {
+            input.pending.push_front(t);
+        }
+        Ok(true)
+    }
+
+    // `__FILE__` and `__LINE__`, they expand to the location of the
+    // outermost macro invocation
+    fn builtin(&mut self, input: &mut Input, token: &PpToken) -> Result<bool, Error> {
+        let (span, file) = token
+            .expansions
+            .last()
+            .map_or((token.span, token.file), |e| (e.span, e.file));
+        let text = match token.name() {
+            Some("__FILE__") => {
+                let name = self.files[file].replace('\\', "\\\\").replace('"', "\\\"");
+                format!("\"{}\"", name)
+            }
+            Some("__LINE__") => span.start.line.to_string(),
+            _ => return Ok(false),
+        };
+        let mut made = self.make(text, token)?;
+        made.hidden = token.hidden.clone();
+        made.expansions = token.expansions.clone();
+        input.pending.push_front(made);
+        Ok(true)
+    }
+
+    // Replace parameters in replacement list by arguments and apply `#`
+    // and `##`
+    fn substitute(
+        &mut self,
+        definition: &Macro,
+        args: Vec<Vec<PpToken>>,
+        invocation: &PpToken,
+    ) -> Result<Vec<PpToken>, Error> {
+        let mut expanded: Vec<Option<Vec<PpToken>>> = vec![None; args.len()];
+        // `None` is a placemarker left by empty argument of `##`
+        let mut output: Vec<Option<PpToken>> = Vec::new();
+        let body = &definition.body;
+        let mut i = 0;
+        while i < body.len() {
+            let token = &body[i];
+            let next = body.get(i + 1);
+            if definition.params.is_some() && token.token == Token::Hash {
+                let param = definition.param(next.expect("checked in definition"));
+                let arg = &args[param.expect("checked in definition")];
+                output.push(Some(self.stringify(arg, token, invocation)?));
+                i += 2;
+            } else if token.token == Token::HashHash {
+                let rhs = &body[i + 1];
+                let lhs = output.pop().flatten();
+                let mut rhs_tokens = match definition.param(rhs) {
+                    Some(param) => {
+                        // GNU `, ## __VA_ARGS__` drops the comma if
+                        // variable arguments are empty
+                        let comma = lhs.as_ref().map(|t| &t.token) == Some(&Token::Comma);
+                        if definition.variadic && param == args.len() - 1 && comma {
+                            if !args[param].is_empty() {
+                                output.push(lhs);
+                                output.extend(args[param].iter().cloned().map(Some));
+                            }
+                            i += 2;
+                            continue;
+                        }
+                        args[param].iter().cloned().map(Some).collect()
+                    }
+                    None if definition.params.is_some() && rhs.token == Token::Hash => {
+                        let param = definition
+                            .param(&body[i + 2])
+                            .expect("checked in definition");
+                        i += 1;
+                        vec![Some(self.stringify(&args[param], rhs, invocation)?)]
+                    }
+                    None => vec![Some(self.body_token(rhs, invocation))],
+                };
+                if rhs_tokens.is_empty() {
+                    rhs_tokens.push(None);
+                }
+                let first = rhs_tokens.remove(0);
+                output.push(match (lhs, first) {
+                    (Some(lhs), Some(rhs)) => Some(self.paste(&lhs, &rhs, token)?),
+                    (lhs, None) => lhs,
+                    (None, rhs) => rhs,
+                });
+                output.extend(rhs_tokens);
+                i += 2;
+            } else if let Some(param) = definition.param(token) {
+                let arg = &args[param];
+                if next.map(|t| &t.token) == Some(&Token::HashHash) {
+                    // operand of `##` is not macro expanded
+                    if arg.is_empty() {
+                        output.push(None);
+                    }
+                    output.extend(arg.iter().cloned().map(Some));
+                } else {
+                    if expanded[param].is_none() {
+                        expanded[param] = Some(self.expand_all(arg.clone())?);
+                    }
+                    let arg = expanded[param].as_ref().expect("expanded above");
+                    let mut arg = arg.clone();
+                    if let Some(first) = arg.first_mut() {
+                        first.space = token.space;
+                    }
+                    output.extend(arg.into_iter().map(Some));
+                }
+                i += 1;
+            } else {
+                output.push(Some(self.body_token(token, invocation)));
+                i += 1;
+            }
+        }
+        Ok(output.into_iter().flatten().collect())
+    }
+
+    // Token of replacement list as it appears in expansion
+    #[inline]
+    fn body_token(&self, token: &PpToken, invocation: &PpToken) -> PpToken {
+        PpToken {
+            expansions: invocation.expansions.clone(),
+            ..token.clone()
+        }
+    }
+
+    // String literal spelling argument, made by `#` operator `at`
+    fn stringify(
+        &self,
+        arg: &[PpToken],
+        at: &PpToken,
+        invocation: &PpToken,
+    ) -> Result<PpToken, Error> {
+        let mut text = String::from("\"");
+        for (i, token) in arg.iter().enumerate() {
+            if i > 0 && token.space {
+                text.push(' ');
+            }
+            match token.token {
+                Token::StringLiteral(_) | Token::CharLiteral(_) => {
+                    for c in token.text.chars() {
+                        if c == '"' || c == '\\' {
+                            text.push('\\');
+                        }
+                        text.push(c);
+                    }
+                }
+                _ => text.push_str(&token.text),
+            }
+        }
+        text.push('"');
+        let mut made = self.make(text, at)?;
+        made.expansions = invocation.expansions.clone();
+        Ok(made)
+    }
+
+    // Token made by pasting two tokens with `##` operator `at`
+    fn paste(&self, lhs: &PpToken, rhs: &PpToken, at: &PpToken) -> Result<PpToken, Error> {
+        let text = format!("{}{}", lhs.text, rhs.text);
+        let mut made = self.make(text, at)?;
+        made.space = lhs.space;
+        made.hidden = lhs
+            .hidden
+            .iter()
+            .filter(|h| rhs.hidden.contains(h))
+            .cloned()
+            .collect();
+        made.expansions = lhs.expansions.clone();
+        Ok(made)
+    }
+
+    // Lex `text` which has to be a single token, it is located at `at`
+    fn make(&self, text: String, at: &PpToken) -> Result<PpToken, Error> {
+        let invalid = || Error::new(ErrorKind::InvalidPaste(text.clone()), at.span.start);
+        let mut tokenizer = Tokenizer::with_options(&text, self.options);
+        let token = match tokenizer.next_token() {
+            Ok(Some(token)) if token.span.end.offset == text.len() => token.token,
+            _ => return Err(invalid()),
+        };
+        if token == Token::LineTerminator {
+            return Err(invalid());
+        }
+        Ok(PpToken {
+            token,
+            text,
+            span: at.span,
+            file: at.file,
+            space: at.space,
+            line_start: false,
+            hidden: Vec::new(),
+            expansions: Vec::new(),
+        })
+    }
+}
+
+// Check `#` and `##` in replacement list
+fn check_body(definition: &Macro) -> Result<(), Error> {
+    let body = &definition.body;
+    for (i, token) in body.iter().enumerate() {
+        match token.token {
+            Token::HashHash if i == 0 || i == body.len() - 1 => {
+                return Err(Error::new(ErrorKind::PasteAtEdge, token.span.start));
+            }
+            Token::Hash
+                if definition.params.is_some()
+                    && body.get(i + 1).and_then(|t| definition.param(t)).is_none() =>
+            {
+                return Err(Error::new(
+                    ErrorKind::StringifyNonParameter,
+                    token.span.start,
+                ));
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[inline]
+fn is_builtin(name: &str) -> bool {
+    name == "__FILE__" || name == "__LINE__"
+}
+
+// Arguments of function-like macro invoked by `name`, `(` is already
+// read. Returns them with the closing `)`.
+fn arguments(
+    input: &mut Input,
+    name: &PpToken,
+    params: usize,
+    variadic: bool,
+) -> Result<(Vec<Vec<PpToken>>, PpToken), Error> {
+    let mut args = vec![Vec::new()];
+    let mut depth = 0;
+    let mut space = false;
+    let rparen = loop {
+        let mut token = input.next()?.ok_or_else(|| {
+            let kind = ErrorKind::UnterminatedMacroCall(name.text.clone());
+            Error::new(kind, name.span.start)
+        })?;
+        token.space |= space;
+        space = false;
+        match token.token {
+            Token::LineTerminator => {
+                space = true;
+                continue;
+            }
+            Token::LParen => depth += 1,
+            Token::RParen if depth == 0 => break token,
+            Token::RParen => depth -= 1,
+            // variable arguments take the rest of commas
+            Token::Comma if depth == 0 && !(variadic && args.len() == params) => {
+                args.push(Vec::new());
+                continue;
+            }
+            _ => {}
+        }
+        args.last_mut().expect("never empty").push(token);
+    };
+    if params == 0 && args.len() == 1 && args[0].is_empty() {
+        args.clear();
+    }
+    // variable arguments may be left out completely
+    if variadic && args.len() == params - 1 {
+        args.push(Vec::new());
+    }
+    if args.len() != params {
+        return Err(Error::new(
+            ErrorKind::MacroArgumentCount {
+                name: name.text.clone(),
+                expected: params,
+                found: args.len(),
+            },
+            name.span.start,
+        ));
+    }
+    Ok((args, rparen))
+}
+
+// Spelling of tokens with single spaces where they had white space
+fn spelling(tokens: &[PpToken]) -> String {
+    let mut text = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 && token.space {
+            text.push(' ');
+        }
+        text.push_str(&token.text);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        token::{Integer, IntegerSuffix, Number},
+        Lexer,
+    };
+
+    fn files(files: &[(&str, &str)]) -> HashMap<String, String> {
+        files
+            .iter()
+            .map(|(name, contents)| (name.to_string(), contents.to_string()))
+            .collect()
+    }
+
+    fn preprocess(source: &str) -> Result<Vec<Token>, Error> {
+        let mut preprocessor = Preprocessor::new(HashMap::new());
+        let tokens = preprocessor.preprocess("main.c", source)?;
+        Ok(tokens.into_iter().map(|t| t.token).collect())
+    }
+
+    // Preprocessed `source` has the same tokens as `expected`
+    fn expands(source: &str, expected: &str) {
+        let mut expected = Lexer::lex(expected).unwrap();
+        expected.retain(|t| *t != Token::LineTerminator);
+        assert_eq!(preprocess(source).unwrap(), expected);
+    }
+
+    fn error(kind: ErrorKind, offset: usize, line: usize, column: usize) -> Error {
+        Error::new(kind, Position::new(offset, line, column))
+    }
+
+    #[test]
+    fn object_like() {
+        expands("#define N 10\nint a[N];\n#undef N\nN", "int a[10]; N");
+        expands("#define EMPTY\n#\n# define ADD a + \\\n b\nEMPTY ADD", "a + b");
+        expands("#define inline __inline\ninline", "__inline");
+    }
+
+    #[test]
+    fn function_like() {
+        expands(
+            "#define MAX(a, b) ((a) > (b) ? (a) : (b))\nMAX(x, y + 1)",
+            "((x) > (y + 1) ? (x) : (y + 1))",
+        );
+        expands("#define f(x) <x>\nf + f (1) f\n(2)", "f + <1> <2>");
+        expands("#define f(x, y) x y\nf((a, b),\n c) f(,)", "(a, b) c");
+        expands("#define f() 1\n#define g (x) x\nf() g", "1 (x) x");
+    }
+
+    #[test]
+    fn recursion() {
+        expands("#define foo foo a\nfoo", "foo a");
+        expands("#define x y\n#define y x\nx y", "x y");
+        expands("#define f(a) a + f(a)\nf(f(1))", "1 + f(1) + f(1 + f(1))");
+    }
+
+    // C11 6.10.3.5 example 3
+    #[test]
+    fn standard_rescanning() {
+        expands(
+            r#"#define x 3
+#define f(a) f(x * (a))
+#undef x
+#define x 2
+#define g f
+#define z z[0]
+#define h g(~
+#define m(a) a(w)
+#define w 0,1
+#define t(a) a
+#define p() int
+#define q(x) x
+#define r(x,y) x ## y
+#define str(x) # x
+f(y+1) + f(f(z)) % t(t(g)(0) + t)(1);
+g(x+(3,4)-w) | h 5) & m
+(f)^m(m);
+p() i[q()] = { q(1), r(2,3), r(4,), r(,5), r(,) };
+char c[2][6] = { str(hello), str() };"#,
+            r#"f(2 * (y+1)) + f(2 * (f(2 * (z[0])))) % f(2 * (0)) + t(1);
+f(2 * (2+(3,4)-0,1)) | f(2 * (~ 5)) & f(2 * (0,1))^m(0,1);
+int i[] = { 1, 23, 4, 5, };
+char c[2][6] = { "hello", "" };"#,
+        );
+    }
+
+    // C11 6.10.3.5 example 4, without the part with stray characters
+    #[test]
+    fn standard_stringify_and_paste() {
+        let source = r#"#define str(s) # s
+#define xstr(s) str(s)
+#define debug(s, t) printf("x" # s "= %d, x" # t "= %s", \
+ x ## s, x ## t)
+#define INCFILE(n) vers ## n
+#define glue(a, b) a ## b
+#define xglue(a, b) glue(a, b)
+#define HIGHLOW "hello"
+#define LOW LOW ", world"
+debug(1, 2);
+fputs(str(strncmp("abc\0d", "abc", '\4') // this goes away
+ == 0), s);
+#include xstr(INCFILE(2).h)
+glue(HIGH, LOW);
+xglue(HIGH, LOW)"#;
+        let expected = r#"printf("x" "1" "= %d, x" "2" "= %s", x1, x2);
+fputs("strncmp(\"abc\\0d\", \"abc\", '\\4') == 0", s);
+int vers2;
+"hello";
+"hello" ", world""#;
+        let mut preprocessor = Preprocessor::new(files(&[("vers2.h", "int vers2;")]));
+        let tokens = preprocessor.preprocess("main.c", source).unwrap();
+        let tokens: Vec<Token> = tokens.into_iter().map(|t| t.token).collect();
+        let mut expected = Lexer::lex(expected).unwrap();
+        expected.retain(|t| *t != Token::LineTerminator);
+        assert_eq!(tokens, expected);
+    }
+
+    // C11 6.10.3.5 example 7
+    #[test]
+    fn standard_variadic() {
+        expands(
+            r#"#define debug(...) fprintf(stderr, __VA_ARGS__)
+#define showlist(...) puts(#__VA_ARGS__)
+#define report(test, ...) ((test)?puts(#test):\
+ printf(__VA_ARGS__))
+debug("Flag");
+debug("X = %d\n", x);
+showlist(The first, second, and third items.);
+report(x>y, "x is %d but y is %d", x, y);"#,
+            r#"fprintf(stderr, "Flag" );
+fprintf(stderr, "X = %d\n", x );
+puts( "The first, second, and third items." );
+((x>y)?puts("x>y"): printf("x is %d but y is %d", x, y));"#,
+        );
+        expands(
+            "#define e(fmt, ...) f(fmt, ## __VA_ARGS__)\ne(a) e(a,) e(a, b, c)",
+            "f(a) f(a) f(a, b, c)",
+        );
+    }
+
+    #[test]
+    fn conditionals() {
+        expands(
+            r#"#define A 2
+#define F(x) x
+#if F(A) > 1 && defined(A) && defined F && !defined(G)
+yes1
+#elif 1 / 0
+no
+#else
+no
+#endif
+#ifdef B
+no
+#elif !defined B
+yes2
+#endif
+#ifndef A
+no
+#else
+yes3
+#endif
+#if 0
+#
+#if 1
+no
+#else
+no
+#endif
+don't lex this
+#bogus
+#elif A == 2
+yes4
+#else
+no
+#endif"#,
+            "yes1 yes2 yes3 yes4",
+        );
+        // conditions after a taken group are not lexed
+        expands("#if 1\nw\n#elif @\n#else @\n#endif\nx", "w x");
+    }
+
+    #[test]
+    fn conditional_errors() {
+        assert_eq!(
+            preprocess("x\n#if 1\n"),
+            Err(error(ErrorKind::UnterminatedConditional, 2, 2, 1))
+        );
+        assert_eq!(
+            preprocess("#endif"),
+            Err(error(
+                ErrorKind::UnmatchedConditional("endif".into()),
+                0,
+                1,
+                1
+            ))
+        );
+        assert_eq!(
+            preprocess("#if 1\n#else\n#elif 1\n#endif"),
+            Err(error(
+                ErrorKind::ConditionalAfterElse("elif".into()),
+                12,
+                3,
+                1
+            ))
+        );
+        assert_eq!(
+            preprocess("#if 1 +\n#endif"),
+            Err(error(ErrorKind::InvalidExpression, 7, 1, 8))
+        );
+        assert_eq!(
+            preprocess("#ifdef\n#endif"),
+            Err(error(ErrorKind::MissingMacroName, 6, 1, 7))
+        );
+    }
+
+    #[test]
+    fn macro_errors() {
+        assert_eq!(
+            preprocess("#define f(x) x\nf(1"),
+            Err(error(
+                ErrorKind::UnterminatedMacroCall("f".into()),
+                15,
+                2,
+                1
+            ))
+        );
+        let count = ErrorKind::MacroArgumentCount {
+            name: "f".into(),
+            expected: 2,
+            found: 1,
+        };
+        assert_eq!(
+            preprocess("#define f(x, y) x\nf(1)"),
+            Err(error(count, 18, 2, 1))
+        );
+        assert_eq!(
+            preprocess("#define c(a, b) a ## b\nc(+, /)"),
+            Err(error(ErrorKind::InvalidPaste("+/".into()), 18, 1, 19))
+        );
+        assert_eq!(
+            preprocess("#define s(x) #y"),
+            Err(error(ErrorKind::StringifyNonParameter, 13, 1, 14))
+        );
+        assert_eq!(
+            preprocess("#define p ## x"),
+            Err(error(ErrorKind::PasteAtEdge, 10, 1, 11))
+        );
+        assert_eq!(
+            preprocess("#define f(x x"),
+            Err(error(ErrorKind::InvalidMacroParameters, 9, 1, 10))
+        );
+        assert_eq!(
+            preprocess("#define 1"),
+            Err(error(ErrorKind::MissingMacroName, 7, 1, 8))
+        );
+        assert_eq!(
+            preprocess("#foo"),
+            Err(error(ErrorKind::InvalidDirective("foo".into()), 0, 1, 1))
+        );
+        assert_eq!(
+            preprocess("\n#error bad  thing"),
+            Err(error(
+                ErrorKind::ErrorDirective("bad thing".into()),
+                1,
+                2,
+                1
+            ))
+        );
+    }
+
+    #[test]
+    fn includes() {
+        let resolver = files(&[
+            ("a.h", "#pragma once\n#define TWICE(x) x x\nint a;\n"),
+            ("b.h", "#include \"a.h\"\n"),
+            ("c.h", "\n#error in c"),
+        ]);
+        let mut preprocessor = Preprocessor::new(resolver);
+        let tokens = preprocessor
+            .preprocess(
+                "main.c",
+                "#include \"a.h\"\n#include <b.h>\nTWICE(y)\n__FILE__ __LINE__",
+            )
+            .unwrap();
+        let files = preprocessor.files();
+        let names: Vec<&str> = tokens.iter().map(|t| files[t.file].as_str()).collect();
+        assert_eq!(
+            names,
+            ["a.h", "a.h", "a.h", "main.c", "main.c", "main.c", "main.c", "main.c"]
+        );
+        let line = Number::Integer(Integer::new(4, 10, IntegerSuffix::default()));
+        assert_eq!(tokens[6].token, Token::NumericLiteral(line));
+        assert_eq!(
+            tokens[3].expansions,
+            vec![Expansion {
+                name: "TWICE".into(),
+                span: Span::new(Position::new(30, 3, 1), Position::new(35, 3, 6)),
+                file: tokens[3].file,
+            }]
+        );
+        assert_eq!(tokens[3].span.start, Position::new(36, 3, 7));
+        assert!(tokens[0].expansions.is_empty());
+
+        assert_eq!(
+            preprocessor.preprocess("main.c", "x\n#include <missing.h>"),
+            Err(error(
+                ErrorKind::IncludeNotFound("missing.h".into()),
+                11,
+                2,
+                10
+            ))
+        );
+        assert_eq!(
+            preprocessor.preprocess("main.c", "#include"),
+            Err(error(ErrorKind::InvalidInclude, 0, 1, 1))
+        );
+        let inner = error(ErrorKind::ErrorDirective("in c".into()), 1, 2, 1);
+        assert_eq!(
+            preprocessor.preprocess("main.c", "\n #include \"c.h\""),
+            Err(error(
+                ErrorKind::InInclude("c.h".into(), Box::new(inner)),
+                2,
+                2,
+                2
+            ))
+        );
+    }
+
+    #[test]
+    fn recursive_include() {
+        let mut preprocessor = Preprocessor::new(files(&[("self.h", "#include \"self.h\"")]));
+        let mut error = preprocessor
+            .preprocess("main.c", "#include \"self.h\"")
+            .unwrap_err();
+        let mut depth = 0;
+        while let ErrorKind::InInclude(_, inner) = error.kind {
+            error = *inner;
+            depth += 1;
+        }
+        assert_eq!(error.kind, ErrorKind::IncludeDepth);
+        assert_eq!(depth, MAX_INCLUDE_DEPTH);
+    }
+
+    #[test]
+    fn line_directives() {
+        let mut preprocessor = Preprocessor::new(HashMap::new());
+        let tokens = preprocessor
+            .preprocess(
+                "main.c",
+                "#line 100 \"x.c\"\n__LINE__ __FILE__\nz\n# 7 \"y.c\" 2\nw",
+            )
+            .unwrap();
+        let files = preprocessor.files();
+        let line = Number::Integer(Integer::new(100, 10, IntegerSuffix::default()));
+        assert_eq!(tokens[0].token, Token::NumericLiteral(line));
+        assert_eq!(files[tokens[2].file], "x.c");
+        assert_eq!(tokens[2].span.start, Position::new(34, 101, 1));
+        assert_eq!(files[tokens[3].file], "y.c");
+        assert_eq!(tokens[3].span.start.line, 7);
+        assert_eq!(
+            preprocess("#line x"),
+            Err(error(ErrorKind::InvalidLineDirective, 0, 1, 1))
+        );
+    }
+
+    #[test]
+    fn standard_version() {
+        let version = |standard| {
+            let options = LexerOptions::for_standard(standard);
+            let mut preprocessor = Preprocessor::with_options(HashMap::new(), options);
+            let tokens = preprocessor
+                .preprocess("a.c", "__STDC_VERSION__")
+                .unwrap();
+            tokens[0].token.clone()
+        };
+        assert_eq!(
+            version(Standard::C89),
+            Token::Identifier(IStr::new("__STDC_VERSION__"))
+        );
+        match version(Standard::C95) {
+            Token::NumericLiteral(Number::Integer(integer)) => assert_eq!(integer.value, 199409),
+            token => panic!("{:?} is not a version", token),
+        }
+        match version(Standard::C23) {
+            Token::NumericLiteral(Number::Integer(integer)) => assert_eq!(integer.value, 202311),
+            token => panic!("{:?} is not a version", token),
+        }
+    }
+
+    #[test]
+    fn keyword_spellings() {
+        let options = LexerOptions::for_standard(Standard::C23);
+        let mut preprocessor = Preprocessor::with_options(HashMap::new(), options);
+        let tokens = preprocessor
+            .preprocess(
+                "a.c",
+                "#define bool int\nbool _Bool\n#if true && defined bool && !defined _Bool\nnullptr\n#endif",
+            )
+            .unwrap();
+        let tokens: Vec<Token> = tokens.into_iter().map(|t| t.token).collect();
+        assert_eq!(
+            tokens,
+            vec![Token::INT, Token::BOOL, Token::NULLPTR, Token::EOF]
+        );
+        assert_eq!(
+            preprocessor.preprocess("b.c", "#if 0x10000000000000000wb\n#endif"),
+            Err(error(ErrorKind::IntegerOverflow, 4, 1, 5))
+        );
+    }
+
+    #[test]
+    fn pragmas_and_warnings() {
+        let mut preprocessor = Preprocessor::new(HashMap::new());
+        preprocessor.define("DEBUG 1").unwrap();
+        assert!(preprocessor.is_defined("DEBUG"));
+        let tokens = preprocessor
+            .preprocess(
+                "main.c",
+                "#pragma pack(1)\n#define A 1\n#define A  1\n#define A 2\n#warning hi\n#if DEBUG\nx\n#endif",
+            )
+            .unwrap();
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            preprocessor.pragmas()[0].tokens,
+            vec![
+                Token::Identifier(IStr::new("pack")),
+                Token::LParen,
+                Token::NumericLiteral(Number::Integer(Integer::new(
+                    1,
+                    10,
+                    IntegerSuffix::default()
+                ))),
+                Token::RParen,
+            ]
+        );
+        assert_eq!(
+            preprocessor.warnings(),
+            &[
+                Warning::new(
+                    ErrorKind::MacroRedefined("A".into()),
+                    Position::new(49, 4, 9)
+                ),
+                Warning::new(
+                    ErrorKind::WarningDirective("hi".into()),
+                    Position::new(53, 5, 1)
+                ),
+            ]
+        );
+    }
+}
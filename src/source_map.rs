@@ -0,0 +1,169 @@
+//! Translation phases that run before tokenization. They change the
+//! input the state machine sees, so offsets in it have to be mapped
+//! back to the source for spans and errors.
+
+use crate::error::{ErrorKind, Warning};
+use std::borrow::Cow;
+
+// Source bytes removed from the input
+#[derive(Debug, Clone, Copy)]
+struct Removed {
+    // offset in the input where bytes were removed
+    at: usize,
+    // number of bytes removed up to and including this place
+    total: usize,
+    // bytes belong to the character before `at`, like the tail of a
+    // trigraph, rather than being between characters like a splice
+    attached: bool,
+}
+
+/// Maps offsets in the input seen by the state machine back to offsets
+/// in the source
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    removed: Vec<Removed>,
+}
+
+impl SourceMap {
+    /// Offset in the source of the character at `offset` of the input,
+    /// removed bytes right before it are skipped
+    #[inline]
+    pub fn start(&self, offset: usize) -> usize {
+        let i = self.removed.partition_point(|r| r.at <= offset);
+        offset + i.checked_sub(1).map_or(0, |i| self.removed[i].total)
+    }
+
+    /// Offset in the source just past the character before `offset` of
+    /// the input, used for exclusive ends of tokens
+    #[inline]
+    pub fn end(&self, offset: usize) -> usize {
+        let i = self
+            .removed
+            .partition_point(|r| r.at < offset || r.at == offset && r.attached);
+        offset + i.checked_sub(1).map_or(0, |i| self.removed[i].total)
+    }
+}
+
+/// Character replacing trigraph `??c`
+#[inline]
+pub(crate) fn trigraph(c: u8) -> Option<u8> {
+    match c {
+        b'=' => Some(b'#'),
+        b'(' => Some(b'['),
+        b'/' => Some(b'\\'),
+        b')' => Some(b']'),
+        b'\'' => Some(b'^'),
+        b'<' => Some(b'{'),
+        b'!' => Some(b'|'),
+        b'>' => Some(b'}'),
+        b'-' => Some(b'~'),
+        _ => None,
+    }
+}
+
+// Length of newline at the start of `source`, `\r\n` is a single one
+#[inline]
+fn newline_len(source: &[u8]) -> usize {
+    match source {
+        [b'\r', b'\n', ..] => 2,
+        [b'\n', ..] | [b'\r', ..] => 1,
+        _ => 0,
+    }
+}
+
+/// Replace trigraphs if enabled (translation phase 1) and delete every
+/// backslash immediately followed by a newline (phase 2). Source which
+/// doesn't change is borrowed as is. Every trigraph is warned about,
+/// warnings are positioned by byte offset only.
+pub fn translate(source: &[u8], trigraphs: bool) -> (Cow<'_, [u8]>, SourceMap, Vec<Warning>) {
+    let mut map = SourceMap::default();
+    let mut warnings = Vec::new();
+    let mut input: Option<Vec<u8>> = None;
+    let mut removed = 0;
+    let mut i = 0;
+    while i < source.len() {
+        let (mut c, mut len) = (source[i], 1);
+        if let [b'?', b'?', t, ..] = source[i..] {
+            if let Some(replacement) = trigraph(t) {
+                let kind = if trigraphs {
+                    ErrorKind::Trigraph(char::from(t))
+                } else {
+                    ErrorKind::IgnoredTrigraph(char::from(t))
+                };
+                warnings.push(Warning::at(kind, i));
+                if trigraphs {
+                    c = replacement;
+                    len = 3;
+                }
+            }
+        }
+        let at = i - removed;
+        let newline = if c == b'\\' {
+            newline_len(&source[i + len..])
+        } else {
+            0
+        };
+        if newline > 0 {
+            input.get_or_insert_with(|| source[..i].to_vec());
+            removed += len + newline;
+            map.removed.push(Removed {
+                at,
+                total: removed,
+                attached: false,
+            });
+        } else if len > 1 {
+            input.get_or_insert_with(|| source[..i].to_vec()).push(c);
+            removed += len - 1;
+            map.removed.push(Removed {
+                at: at + 1,
+                total: removed,
+                attached: true,
+            });
+        } else if let Some(input) = input.as_mut() {
+            input.push(c);
+        }
+        i += len + newline;
+    }
+    let input = input.map_or(Cow::Borrowed(source), Cow::Owned);
+    (input, map, warnings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets() {
+        let (input, map, _) = translate(b"ab\\\ncd\\\r\n\\\ne", false);
+        assert_eq!(&input[..], b"abcde");
+        assert_eq!(map.start(1), 1);
+        assert_eq!(map.start(2), 4);
+        assert_eq!(map.end(2), 2);
+        assert_eq!(map.start(4), 11);
+        assert_eq!(map.end(4), 6);
+        assert_eq!(map.end(5), 12);
+    }
+
+    #[test]
+    fn borrowed() {
+        let (input, _, warnings) = translate(b"a \\ b ??= \n", false);
+        assert!(matches!(input, Cow::Borrowed(_)));
+        assert_eq!(
+            warnings,
+            vec![Warning::at(ErrorKind::IgnoredTrigraph('='), 6)]
+        );
+    }
+
+    #[test]
+    fn trigraphs() {
+        let (input, map, warnings) = translate(b"???=x??/\n??>", true);
+        assert_eq!(&input[..], b"?#x}");
+        assert_eq!(warnings.len(), 3);
+        assert_eq!(map.start(1), 1);
+        assert_eq!(map.end(2), 4);
+        assert_eq!(map.start(2), 4);
+        assert_eq!(map.end(3), 5);
+        assert_eq!(map.start(3), 9);
+        assert_eq!(map.end(4), 12);
+    }
+}
@@ -0,0 +1,69 @@
+/// Location of a single byte in the source
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Position {
+    /// Byte offset from the start of input
+    pub offset: usize,
+    /// Line number, starting at 1
+    pub line: usize,
+    /// Column in characters, starting at 1
+    pub column: usize,
+}
+
+impl Position {
+    /// Create new position
+    #[inline]
+    pub fn new(offset: usize, line: usize, column: usize) -> Self {
+        Self {
+            offset,
+            line,
+            column,
+        }
+    }
+
+    /// Move position forward to byte offset `to` of `input`, counting
+    /// line breaks on the way. `\r\n` is counted as a single line break.
+    #[inline]
+    pub(crate) fn advance(&mut self, input: &[u8], to: usize) {
+        for i in self.offset..to {
+            match input[i] {
+                b'\r' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                b'\n' => {
+                    if i == 0 || input[i - 1] != b'\r' {
+                        self.line += 1;
+                        self.column = 1;
+                    }
+                }
+                // UTF-8 continuation bytes don't start a new character
+                b if b & 0xC0 == 0x80 => {}
+                _ => self.column += 1,
+            }
+        }
+        self.offset = to;
+    }
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self::new(0, 1, 1)
+    }
+}
+
+/// Range of the source covered by a token, `end` is exclusive
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct Span {
+    /// Position of the first byte of the token
+    pub start: Position,
+    /// Position just past the last byte of the token
+    pub end: Position,
+}
+
+impl Span {
+    /// Create new span
+    #[inline]
+    pub fn new(start: Position, end: Position) -> Self {
+        Self { start, end }
+    }
+}
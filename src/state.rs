@@ -1,3 +1,5 @@
+#![allow(clippy::upper_case_acronyms)]
+
 pub trait State {
     fn is_final(&self) -> bool;
 }
@@ -15,11 +17,18 @@ accept_state!(DecimalDigitsAcc);
 accept_state!(DecimalExponentAcc);
 accept_state!(DecimalExponentSignedAcc);
 accept_state!(DotPart);
+accept_state!(EllipsisAcc);
 accept_state!(String);
 accept_state!(ExclamationAcc);
+accept_state!(HashAcc);
 accept_state!(HELL);
+accept_state!(FloatSuffixAcc);
 accept_state!(HexAcc);
+accept_state!(HexExponentAcc);
+accept_state!(HexExponentSignedAcc);
+accept_state!(HexFractionAcc);
 accept_state!(Identifier);
+accept_state!(IntegerSuffixAcc);
 accept_state!(LBraceAcc);
 accept_state!(LtAcc);
 accept_state!(LineTerminator);
@@ -43,6 +52,7 @@ accept_state!(TildeAcc);
 accept_state!(WhiteSpace);
 
 state!(Tilde);
+state!(Hash);
 state!(Semicolon);
 state!(And);
 state!(Assign);
@@ -62,9 +72,16 @@ state!(Decimal);
 state!(DecimalDigits);
 state!(DecimalExponent);
 state!(DecimalExponentSigned);
+state!(Dot);
+state!(DotDot);
 state!(Exclamation);
+state!(FloatSuffix);
 state!(Hex);
+state!(HexExponent);
+state!(HexExponentSigned);
+state!(HexFraction);
 state!(InputElementDiv);
+state!(IntegerSuffix);
 state!(Lt);
 state!(Minus);
 state!(MultiLineComment);
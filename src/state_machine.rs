@@ -1,13 +1,19 @@
 use crate::{
     equivalence::{Equivalence, EQUIVALENCE_CLASS},
-    error::Error,
+    error::{Error, ErrorKind, Warning},
     identifier,
-    number::{parse_exponent, parse_number, parse_number_decimal, parse_number_radix},
+    number::{parse_float, parse_integer},
+    options::{LexerOptions, Standard},
+    source_map::{self, SourceMap},
+    span::{Position, Span},
     state::*,
     string,
-    token::Token,
+    symbols::SymbolTable,
+    token::{Encoding, HeaderName, SpannedToken, Token},
+    trivia::Trivia,
 };
-use std::str;
+use internship::IStr;
+use std::{borrow::Cow, mem, str};
 
 #[derive(Debug)]
 struct StateMachine<S: State> {
@@ -26,6 +32,7 @@ impl<S: State> StateMachine<S> {
     }
 }
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug)]
 enum StateMachineWrapper {
     LineTerminator(StateMachine<LineTerminator>),
@@ -33,6 +40,8 @@ enum StateMachineWrapper {
     SingleLineCommentAcc(StateMachine<SingleLineCommentAcc>),
     MultiLineCommentAcc(StateMachine<MultiLineCommentAcc>),
     DotPart(StateMachine<DotPart>),
+    EllipsisAcc(StateMachine<EllipsisAcc>),
+    HELL(StateMachine<HELL>),
     Comma(StateMachine<Comma>),
     CommaAcc(StateMachine<CommaAcc>),
     Semicolon(StateMachine<Semicolon>),
@@ -49,6 +58,8 @@ enum StateMachineWrapper {
     OrAcc(StateMachine<OrAcc>),
     Tilde(StateMachine<Tilde>),
     TildeAcc(StateMachine<TildeAcc>),
+    Hash(StateMachine<Hash>),
+    HashAcc(StateMachine<HashAcc>),
     QuestionMark(StateMachine<QuestionMark>),
     QuestionMarkAcc(StateMachine<QuestionMarkAcc>),
     ColonAcc(StateMachine<ColonAcc>),
@@ -63,6 +74,11 @@ enum StateMachineWrapper {
     DecimalExponentAcc(StateMachine<DecimalExponentAcc>),
     DecimalExponentSignedAcc(StateMachine<DecimalExponentSignedAcc>),
     Identifier(StateMachine<Identifier>),
+    IntegerSuffixAcc(StateMachine<IntegerSuffixAcc>),
+    FloatSuffixAcc(StateMachine<FloatSuffixAcc>),
+    HexFractionAcc(StateMachine<HexFractionAcc>),
+    HexExponentAcc(StateMachine<HexExponentAcc>),
+    HexExponentSignedAcc(StateMachine<HexExponentSignedAcc>),
     SlashAcc(StateMachine<SlashAcc>),
     LBraceAcc(StateMachine<LBraceAcc>),
     RBraceAcc(StateMachine<RBraceAcc>),
@@ -102,12 +118,24 @@ enum StateMachineWrapper {
     DecimalDigits(StateMachine<DecimalDigits>),
     DecimalExponent(StateMachine<DecimalExponent>),
     DecimalExponentSigned(StateMachine<DecimalExponentSigned>),
+    IntegerSuffix(StateMachine<IntegerSuffix>),
+    FloatSuffix(StateMachine<FloatSuffix>),
+    HexFraction(StateMachine<HexFraction>),
+    HexExponent(StateMachine<HexExponent>),
+    HexExponentSigned(StateMachine<HexExponentSigned>),
+    Dot(StateMachine<Dot>),
+    DotDot(StateMachine<DotDot>),
 }
 
 edge!(InputElementDiv, LineTerminator);
 edge!(InputElementDiv, WhiteSpace);
 edge!(InputElementDiv, Slash);
-edge!(InputElementDiv, DotPart);
+edge!(InputElementDiv, Dot);
+edge!(Dot, DotPart);
+edge!(Dot, DotDot);
+edge!(Dot, DecimalDigits);
+edge!(DotDot, DotPart);
+edge!(DotDot, EllipsisAcc);
 edge!(Slash, SlashAcc);
 edge!(Slash, SingleLineComment);
 edge!(Slash, MultiLineComment);
@@ -137,6 +165,8 @@ edge!(InputElementDiv, QuestionMark);
 edge!(QuestionMark, QuestionMarkAcc);
 edge!(InputElementDiv, Tilde);
 edge!(Tilde, TildeAcc);
+edge!(InputElementDiv, Hash);
+edge!(Hash, HashAcc);
 edge!(InputElementDiv, Lt);
 edge!(Lt, LtAcc);
 edge!(InputElementDiv, Gt);
@@ -163,6 +193,8 @@ edge!(And, AndAcc);
 edge!(InputElementDiv, And);
 edge!(InputElementDiv, SawZero);
 edge!(SawZero, Decimal);
+edge!(SawZero, DecimalDigits);
+edge!(SawZero, DecimalExponent);
 edge!(SawZero, DecimalAcc);
 edge!(InputElementDiv, Decimal);
 edge!(Decimal, DecimalAcc);
@@ -183,7 +215,27 @@ edge!(InputElementDiv, Octal);
 edge!(Octal, OctalAcc);
 edge!(SawZero, Octal);
 edge!(MultiLineCommentStar, MultiLineComment);
-edge!(LineTerminator, HELL);
+edge!(SawZero, IntegerSuffix);
+edge!(Decimal, IntegerSuffix);
+edge!(Octal, IntegerSuffix);
+edge!(Hex, IntegerSuffix);
+edge!(Binary, IntegerSuffix);
+edge!(IntegerSuffix, IntegerSuffixAcc);
+edge!(DecimalDigits, FloatSuffix);
+edge!(DecimalExponent, FloatSuffix);
+edge!(DecimalExponentSigned, FloatSuffix);
+edge!(FloatSuffix, FloatSuffixAcc);
+edge!(Hex, HexFraction);
+edge!(Hex, HexExponent);
+edge!(HexFraction, HexExponent);
+edge!(HexFraction, FloatSuffix);
+edge!(HexFraction, HexFractionAcc);
+edge!(HexExponent, HexExponentSigned);
+edge!(HexExponent, FloatSuffix);
+edge!(HexExponent, HexExponentAcc);
+edge!(HexExponentSigned, FloatSuffix);
+edge!(HexExponentSigned, HexExponentSignedAcc);
+edge!(InputElementDiv, HELL);
 
 impl StateMachineWrapper {
     #[inline]
@@ -192,7 +244,23 @@ impl StateMachineWrapper {
             (StateMachineWrapper::InputElementDiv(s), Equivalence::LineTerminator) => StateMachineWrapper::LineTerminator(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::White) => StateMachineWrapper::WhiteSpace(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Slash) => StateMachineWrapper::Slash(s.into()), // /
-            (StateMachineWrapper::InputElementDiv(s), Equivalence::Dot) => StateMachineWrapper::DotPart(s.into()),
+            (StateMachineWrapper::InputElementDiv(s), Equivalence::Dot) => StateMachineWrapper::Dot(s.into()),
+            (StateMachineWrapper::InputElementDiv(s), Equivalence::HELL) => StateMachineWrapper::HELL(s.into()),
+            (StateMachineWrapper::InputElementDiv(s), Equivalence::BackTick) => StateMachineWrapper::HELL(s.into()),
+            // dot, ellipsis or fractional constant like `.5`
+            (StateMachineWrapper::Dot(s), Equivalence::Zero) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::One) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Two) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Three) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Four) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Five) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Six) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Seven) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::EightNine) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::Dot(s), Equivalence::Dot) => StateMachineWrapper::DotDot(s.into()),
+            (StateMachineWrapper::Dot(s), _) => StateMachineWrapper::DotPart(s.into()),
+            (StateMachineWrapper::DotDot(s), Equivalence::Dot) => StateMachineWrapper::EllipsisAcc(s.into()),
+            (StateMachineWrapper::DotDot(s), _) => StateMachineWrapper::DotPart(s.into()),
             (StateMachineWrapper::Slash(s), Equivalence::Assign) => StateMachineWrapper::SlashAcc(s.into()), // /
             (StateMachineWrapper::Slash(s), Equivalence::Slash) => StateMachineWrapper::SingleLineComment(s.into()), // //
             (StateMachineWrapper::Slash(s), Equivalence::Multi) => StateMachineWrapper::MultiLineComment(s.into()),
@@ -200,6 +268,7 @@ impl StateMachineWrapper {
 
             // single line comment
             (StateMachineWrapper::SingleLineComment(s), Equivalence::LineTerminator) => StateMachineWrapper::SingleLineCommentAcc(s.into()),
+            (StateMachineWrapper::SingleLineComment(s), Equivalence::Eof) => StateMachineWrapper::SingleLineCommentAcc(s.into()),
             (StateMachineWrapper::SingleLineComment(s), _) => StateMachineWrapper::SingleLineComment(s),
 
             // Multiline comment
@@ -210,6 +279,7 @@ impl StateMachineWrapper {
             (StateMachineWrapper::MultiLineComment(s), _) => StateMachineWrapper::MultiLineComment(s),
             (StateMachineWrapper::MultiLineCommentStar(s), _) => StateMachineWrapper::MultiLineComment(s.into()),
             // Identifier
+            (StateMachineWrapper::InputElementDiv(s), Equivalence::P) => StateMachineWrapper::Identifier(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Letter) => StateMachineWrapper::Identifier(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::A) => StateMachineWrapper::Identifier(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::B) => StateMachineWrapper::Identifier(s.into()),
@@ -241,6 +311,9 @@ impl StateMachineWrapper {
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Comma) => StateMachineWrapper::Comma(s.into()),
             (StateMachineWrapper::Comma(s), _) => StateMachineWrapper::CommaAcc(s.into()),
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Colon) => StateMachineWrapper::Colon(s.into()),
+            // digraph `:>`
+            (StateMachineWrapper::Colon(s), Equivalence::Gt) => StateMachineWrapper::Colon(s),
+            (StateMachineWrapper::Colon(s), Equivalence::Colon) => StateMachineWrapper::Colon(s),
             (StateMachineWrapper::Colon(s), _) => StateMachineWrapper::ColonAcc(s.into()),
 
             (StateMachineWrapper::InputElementDiv(s), Equivalence::QuestionMark) => StateMachineWrapper::QuestionMark(s.into()),
@@ -249,10 +322,18 @@ impl StateMachineWrapper {
 
             (StateMachineWrapper::Tilde(s), _) => StateMachineWrapper::TildeAcc(s.into()),
 
+            // `#` and `##`
+            (StateMachineWrapper::InputElementDiv(s), Equivalence::Hash) => StateMachineWrapper::Hash(s.into()),
+            (StateMachineWrapper::Hash(s), Equivalence::Hash) => StateMachineWrapper::Hash(s),
+            (StateMachineWrapper::Hash(s), _) => StateMachineWrapper::HashAcc(s.into()),
+
             // Less than
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Lt) => StateMachineWrapper::Lt(s.into()),
             (StateMachineWrapper::Lt(s), Equivalence::Assign) => StateMachineWrapper::Lt(s),
             (StateMachineWrapper::Lt(s), Equivalence::Lt) => StateMachineWrapper::Lt(s),
+            // digraphs `<:` and `<%`
+            (StateMachineWrapper::Lt(s), Equivalence::Colon) => StateMachineWrapper::Lt(s),
+            (StateMachineWrapper::Lt(s), Equivalence::Mod) => StateMachineWrapper::Lt(s),
             (StateMachineWrapper::Lt(s), _) => StateMachineWrapper::LtAcc(s.into()),
 
             // Greater than
@@ -297,6 +378,9 @@ impl StateMachineWrapper {
             (StateMachineWrapper::InputElementDiv(s), Equivalence::Mod) => StateMachineWrapper::Mod(s.into()),
             (StateMachineWrapper::Mod(s), Equivalence::Mod) => StateMachineWrapper::Mod(s),
             (StateMachineWrapper::Mod(s), Equivalence::Assign) => StateMachineWrapper::Mod(s),
+            // digraphs `%>`, `%:` and `%:%:`
+            (StateMachineWrapper::Mod(s), Equivalence::Gt) => StateMachineWrapper::Mod(s),
+            (StateMachineWrapper::Mod(s), Equivalence::Colon) => StateMachineWrapper::Mod(s),
             (StateMachineWrapper::Mod(s), _) => StateMachineWrapper::ModAcc(s.into()),
 
             // and
@@ -336,6 +420,7 @@ impl StateMachineWrapper {
             (StateMachineWrapper::SawZero(s), Equivalence::O) => StateMachineWrapper::Octal(s.into()),
 
             (StateMachineWrapper::SawZero(s), Equivalence::X) => StateMachineWrapper::Hex(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::Zero) => StateMachineWrapper::Decimal(s.into()),
             (StateMachineWrapper::SawZero(s), Equivalence::One) => StateMachineWrapper::Decimal(s.into()),
             (StateMachineWrapper::SawZero(s), Equivalence::Two) => StateMachineWrapper::Decimal(s.into()),
             (StateMachineWrapper::SawZero(s), Equivalence::Three) => StateMachineWrapper::Decimal(s.into()),
@@ -345,6 +430,15 @@ impl StateMachineWrapper {
             (StateMachineWrapper::SawZero(s), Equivalence::Six) => StateMachineWrapper::Decimal(s.into()),
             (StateMachineWrapper::SawZero(s), Equivalence::Seven) => StateMachineWrapper::Decimal(s.into()),
             (StateMachineWrapper::SawZero(s), Equivalence::EightNine) => StateMachineWrapper::Decimal(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::Dot) => StateMachineWrapper::DecimalDigits(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::E) => StateMachineWrapper::DecimalExponent(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::A) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::C) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::D) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::F) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::P) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::SawZero(s), Equivalence::DigitSeparator) => StateMachineWrapper::SawZero(s),
             (StateMachineWrapper::SawZero(s), _) => StateMachineWrapper::DecimalAcc(s.into()),
             (StateMachineWrapper::Decimal(s), Equivalence::Zero) => StateMachineWrapper::Decimal(s),
             (StateMachineWrapper::Decimal(s), Equivalence::One) => StateMachineWrapper::Decimal(s),
@@ -357,6 +451,16 @@ impl StateMachineWrapper {
             (StateMachineWrapper::Decimal(s), Equivalence::EightNine) => StateMachineWrapper::Decimal(s),
             (StateMachineWrapper::Decimal(s), Equivalence::Dot) => StateMachineWrapper::DecimalDigits(s.into()),
             (StateMachineWrapper::Decimal(s), Equivalence::E) => StateMachineWrapper::DecimalExponent(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::A) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::B) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::C) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::D) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::F) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::X) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::O) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::P) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Decimal(s), Equivalence::DigitSeparator) => StateMachineWrapper::Decimal(s),
             (StateMachineWrapper::Decimal(s), _) => StateMachineWrapper::DecimalAcc(s.into()),
             (StateMachineWrapper::DecimalDigits(s), Equivalence::Zero) => StateMachineWrapper::DecimalDigits(s),
             (StateMachineWrapper::DecimalDigits(s), Equivalence::One) => StateMachineWrapper::DecimalDigits(s),
@@ -368,6 +472,16 @@ impl StateMachineWrapper {
             (StateMachineWrapper::DecimalDigits(s), Equivalence::Seven) => StateMachineWrapper::DecimalDigits(s),
             (StateMachineWrapper::DecimalDigits(s), Equivalence::EightNine) => StateMachineWrapper::DecimalDigits(s),
             (StateMachineWrapper::DecimalDigits(s), Equivalence::E) => StateMachineWrapper::DecimalExponent(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalDigits(s), Equivalence::DigitSeparator) => StateMachineWrapper::DecimalDigits(s),
             (StateMachineWrapper::DecimalDigits(s), _) => StateMachineWrapper::DecimalDigitsAcc(s.into()),
             (StateMachineWrapper::DecimalExponent(s), Equivalence::Zero) => StateMachineWrapper::DecimalExponent(s),
             (StateMachineWrapper::DecimalExponent(s), Equivalence::One) => StateMachineWrapper::DecimalExponent(s),
@@ -380,6 +494,17 @@ impl StateMachineWrapper {
             (StateMachineWrapper::DecimalExponent(s), Equivalence::EightNine) => StateMachineWrapper::DecimalExponent(s),
             (StateMachineWrapper::DecimalExponent(s), Equivalence::Minus) => StateMachineWrapper::DecimalExponentSigned(s.into()),
             (StateMachineWrapper::DecimalExponent(s), Equivalence::Plus) => StateMachineWrapper::DecimalExponentSigned(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::E) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponent(s), Equivalence::DigitSeparator) => StateMachineWrapper::DecimalExponent(s),
             (StateMachineWrapper::DecimalExponent(s), _) => StateMachineWrapper::DecimalExponentAcc(s.into()),
             (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::Zero) => StateMachineWrapper::DecimalExponentSigned(s),
             (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::One) => StateMachineWrapper::DecimalExponentSigned(s),
@@ -390,6 +515,17 @@ impl StateMachineWrapper {
             (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::Six) => StateMachineWrapper::DecimalExponentSigned(s),
             (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::Seven) => StateMachineWrapper::DecimalExponentSigned(s),
             (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::EightNine) => StateMachineWrapper::DecimalExponentSigned(s),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::E) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::DecimalExponentSigned(s), Equivalence::DigitSeparator) => StateMachineWrapper::DecimalExponentSigned(s),
             (StateMachineWrapper::DecimalExponentSigned(s), _) => StateMachineWrapper::DecimalExponentSignedAcc(s.into()),
             (StateMachineWrapper::Octal(s), Equivalence::Zero) => StateMachineWrapper::Octal(s),
             (StateMachineWrapper::Octal(s), Equivalence::One) => StateMachineWrapper::Octal(s),
@@ -399,6 +535,18 @@ impl StateMachineWrapper {
             (StateMachineWrapper::Octal(s), Equivalence::Five) => StateMachineWrapper::Octal(s),
             (StateMachineWrapper::Octal(s), Equivalence::Six) => StateMachineWrapper::Octal(s),
             (StateMachineWrapper::Octal(s), Equivalence::Seven) => StateMachineWrapper::Octal(s),
+            (StateMachineWrapper::Octal(s), Equivalence::EightNine) => StateMachineWrapper::Octal(s),
+            (StateMachineWrapper::Octal(s), Equivalence::A) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::B) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::C) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::D) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::E) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::F) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::X) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::O) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::P) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Octal(s), Equivalence::DigitSeparator) => StateMachineWrapper::Octal(s),
             (StateMachineWrapper::Octal(s), _) => StateMachineWrapper::OctalAcc(s.into()),
             (StateMachineWrapper::Hex(s), Equivalence::Zero) => StateMachineWrapper::Hex(s),
             (StateMachineWrapper::Hex(s), Equivalence::One) => StateMachineWrapper::Hex(s),
@@ -415,12 +563,145 @@ impl StateMachineWrapper {
             (StateMachineWrapper::Hex(s), Equivalence::D) => StateMachineWrapper::Hex(s),
             (StateMachineWrapper::Hex(s), Equivalence::E) => StateMachineWrapper::Hex(s),
             (StateMachineWrapper::Hex(s), Equivalence::F) => StateMachineWrapper::Hex(s),
+            (StateMachineWrapper::Hex(s), Equivalence::X) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Hex(s), Equivalence::O) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Hex(s), Equivalence::Dot) => StateMachineWrapper::HexFraction(s.into()),
+            (StateMachineWrapper::Hex(s), Equivalence::P) => StateMachineWrapper::HexExponent(s.into()),
+            (StateMachineWrapper::Hex(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Hex(s), Equivalence::DigitSeparator) => StateMachineWrapper::Hex(s),
             (StateMachineWrapper::Hex(s), _) => StateMachineWrapper::HexAcc(s.into()),
+            // hexadecimal floating constant, binary exponent is required
+            (StateMachineWrapper::HexFraction(s), Equivalence::Zero) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::One) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Two) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Three) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Four) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Five) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Six) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Seven) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::EightNine) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::A) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::B) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::C) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::D) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::E) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::F) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), Equivalence::P) => StateMachineWrapper::HexExponent(s.into()),
+            (StateMachineWrapper::HexFraction(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexFraction(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexFraction(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexFraction(s), Equivalence::DigitSeparator) => StateMachineWrapper::HexFraction(s),
+            (StateMachineWrapper::HexFraction(s), _) => StateMachineWrapper::HexFractionAcc(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Zero) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::One) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Two) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Three) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Four) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Five) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Six) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Seven) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::EightNine) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Minus) => StateMachineWrapper::HexExponentSigned(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Plus) => StateMachineWrapper::HexExponentSigned(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::E) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponent(s), Equivalence::DigitSeparator) => StateMachineWrapper::HexExponent(s),
+            (StateMachineWrapper::HexExponent(s), _) => StateMachineWrapper::HexExponentAcc(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Zero) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::One) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Two) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Three) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Four) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Five) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Six) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Seven) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::EightNine) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::E) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s.into()),
+            (StateMachineWrapper::HexExponentSigned(s), Equivalence::DigitSeparator) => StateMachineWrapper::HexExponentSigned(s),
+            (StateMachineWrapper::HexExponentSigned(s), _) => StateMachineWrapper::HexExponentSignedAcc(s.into()),
             (StateMachineWrapper::Binary(s), Equivalence::One) => StateMachineWrapper::Binary(s),
             (StateMachineWrapper::Binary(s), Equivalence::Zero) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Two) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Three) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Four) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Five) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Six) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::Seven) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::EightNine) => StateMachineWrapper::Binary(s),
+            (StateMachineWrapper::Binary(s), Equivalence::A) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::B) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::C) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::D) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::E) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::F) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::X) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::O) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::P) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s.into()),
+            (StateMachineWrapper::Binary(s), Equivalence::DigitSeparator) => StateMachineWrapper::Binary(s),
             (StateMachineWrapper::Binary(s), _) => StateMachineWrapper::BinaryAcc(s.into()),
-            (_, Equivalence::HELL) => StateMachineWrapper::InputElementDiv(StateMachine::<InputElementDiv>::new()),
-            a => unreachable!("Invalid state:  {:?} ", a)
+            // integer suffix, validated once the whole literal is known
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::A) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::B) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::C) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::D) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::E) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::F) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::X) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::O) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::P) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Letter) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Zero) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::One) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Two) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Three) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Four) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Five) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Six) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::Seven) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), Equivalence::EightNine) => StateMachineWrapper::IntegerSuffix(s),
+            (StateMachineWrapper::IntegerSuffix(s), _) => StateMachineWrapper::IntegerSuffixAcc(s.into()),
+            // floating suffix, validated once the whole literal is known
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::A) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::B) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::C) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::D) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::E) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::F) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::X) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::O) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::P) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Letter) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Zero) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::One) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Two) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Three) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Four) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Five) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Six) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::Seven) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), Equivalence::EightNine) => StateMachineWrapper::FloatSuffix(s),
+            (StateMachineWrapper::FloatSuffix(s), _) => StateMachineWrapper::FloatSuffixAcc(s.into()),
+            // Final states are never stepped and `Eof` can't start a token,
+            // anything else left is a character that can't be lexed
+            _ => StateMachineWrapper::HELL(StateMachine { state: HELL }),
         }
     }
 
@@ -442,6 +723,8 @@ impl StateMachineWrapper {
             StateMachineWrapper::LBracketAcc(n) => n.is_final(),
             StateMachineWrapper::RBracketAcc(n) => n.is_final(),
             StateMachineWrapper::DotPart(n) => n.is_final(),
+            StateMachineWrapper::EllipsisAcc(n) => n.is_final(),
+            StateMachineWrapper::HELL(n) => n.is_final(),
             StateMachineWrapper::Comma(n) => n.is_final(),
             StateMachineWrapper::CommaAcc(n) => n.is_final(),
             StateMachineWrapper::Semicolon(n) => n.is_final(),
@@ -458,6 +741,8 @@ impl StateMachineWrapper {
             StateMachineWrapper::OrAcc(n) => n.is_final(),
             StateMachineWrapper::Tilde(n) => n.is_final(),
             StateMachineWrapper::TildeAcc(n) => n.is_final(),
+            StateMachineWrapper::Hash(n) => n.is_final(),
+            StateMachineWrapper::HashAcc(n) => n.is_final(),
             StateMachineWrapper::QuestionMark(n) => n.is_final(),
             StateMachineWrapper::QuestionMarkAcc(n) => n.is_final(),
             StateMachineWrapper::ColonAcc(n) => n.is_final(),
@@ -472,6 +757,11 @@ impl StateMachineWrapper {
             StateMachineWrapper::DecimalExponentAcc(n) => n.is_final(),
             StateMachineWrapper::DecimalExponentSignedAcc(n) => n.is_final(),
             StateMachineWrapper::Identifier(n) => n.is_final(),
+            StateMachineWrapper::IntegerSuffixAcc(n) => n.is_final(),
+            StateMachineWrapper::FloatSuffixAcc(n) => n.is_final(),
+            StateMachineWrapper::HexFractionAcc(n) => n.is_final(),
+            StateMachineWrapper::HexExponentAcc(n) => n.is_final(),
+            StateMachineWrapper::HexExponentSignedAcc(n) => n.is_final(),
             StateMachineWrapper::SlashAcc(n) => n.is_final(),
             StateMachineWrapper::RBrace(n) => n.is_final(),
             StateMachineWrapper::RBraceAcc(n) => n.is_final(),
@@ -501,39 +791,503 @@ impl StateMachineWrapper {
             StateMachineWrapper::DecimalDigits(n) => n.is_final(),
             StateMachineWrapper::DecimalExponent(n) => n.is_final(),
             StateMachineWrapper::DecimalExponentSigned(n) => n.is_final(),
+            StateMachineWrapper::IntegerSuffix(n) => n.is_final(),
+            StateMachineWrapper::FloatSuffix(n) => n.is_final(),
+            StateMachineWrapper::HexFraction(n) => n.is_final(),
+            StateMachineWrapper::HexExponent(n) => n.is_final(),
+            StateMachineWrapper::HexExponentSigned(n) => n.is_final(),
+            StateMachineWrapper::Dot(n) => n.is_final(),
+            StateMachineWrapper::DotDot(n) => n.is_final(),
         }
     }
 }
 
-#[inline]
-pub fn parse(input: &str) -> Result<Vec<Token>, Error> {
-    let mut st = StateMachineWrapper::InputElementDiv(StateMachine::<InputElementDiv>::new());
-    let input = input.as_bytes();
-    let mut tokens = Vec::with_capacity(input.len());
-
-    let mut c_src: usize = 0;
-    let mut token_len: u64 = 0;
-    while c_src < input.len() {
-        while !st.is_final() {
-            let ch = unsafe { *input.get_unchecked(c_src) };
-            st = st.step(EQUIVALENCE_CLASS[ch as usize]);
-            c_src += 1;
-            token_len += 1;
+/// Runs the state machine over input producing one token at a time
+#[derive(Debug, Clone)]
+pub struct Tokenizer<'a> {
+    // source as given and input after replacing trigraphs and splicing
+    // lines, which is what
+    // tokens are lexed from
+    source: &'a [u8],
+    input: Cow<'a, [u8]>,
+    map: SourceMap,
+    c_src: usize,
+    pos: Position,
+    options: LexerOptions,
+    warnings: Vec<Warning>,
+    trigraphs: Trigraphs,
+    // next token is the first one on its line
+    line_start: bool,
+    include: Include,
+    // presumed file name and line number minus the physical one set by
+    // `#line`
+    file: Option<IStr>,
+    line_delta: isize,
+    // a group of conditional compilation is being skipped, `#line` in it
+    // is not handled
+    skipping: bool,
+    // comments lexed since they were last taken, only kept when lexing
+    // losslessly
+    comments: Option<Vec<(Trivia, Span)>>,
+    symbols: SymbolTable,
+}
+
+// Warnings about trigraphs in the whole input, the ones before `released`
+// are reported once lexing gets past them
+#[derive(Debug, Clone)]
+struct Trigraphs {
+    warnings: Vec<Warning>,
+    released: usize,
+}
+
+impl Trigraphs {
+    // Report warnings before `offset` of the source on presumed lines
+    #[inline]
+    fn release(&mut self, offset: usize, line_delta: isize, warnings: &mut Vec<Warning>) {
+        while let Some(warning) = self.warnings.get(self.released) {
+            if warning.position.offset >= offset {
+                break;
+            }
+            let mut warning = warning.clone();
+            warning.position.line = (warning.position.line as isize + line_delta) as usize;
+            warnings.push(warning);
+            self.released += 1;
+        }
+    }
+}
+
+// Progress through `# include` at the start of a line, header names are
+// lexed only right after it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Include {
+    None,
+    Hash,
+    Directive,
+}
+
+impl Include {
+    #[inline]
+    fn next(self, token: &Token, line_start: bool) -> Self {
+        match (self, token) {
+            (_, Token::Hash) if line_start => Include::Hash,
+            (Include::Hash, Token::Identifier(name))
+                if matches!(&**name, "include" | "include_next" | "import") =>
+            {
+                Include::Directive
+            }
+            _ => Include::None,
+        }
+    }
+}
+
+impl<'a> Tokenizer<'a> {
+    #[inline]
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Self {
+        let source = input.as_bytes();
+        let (input, map, mut trigraphs) = source_map::translate(source, options.trigraphs);
+        let mut pos = Position::default();
+        for warning in &mut trigraphs {
+            warning.locate(source, pos);
+            pos = warning.position;
+        }
+        Self {
+            source,
+            input,
+            map,
+            c_src: 0,
+            pos: Position::default(),
+            options,
+            warnings: Vec::new(),
+            trigraphs: Trigraphs {
+                warnings: trigraphs,
+                released: 0,
+            },
+            line_start: true,
+            include: Include::None,
+            file: None,
+            line_delta: 0,
+            skipping: false,
+            comments: None,
+            symbols: SymbolTable::new(),
+        }
+    }
+
+    /// Create tokenizer that keeps comments and line comments apart
+    /// from the line terminators after them, so that everything between
+    /// tokens is either a comment or white space. `#line` is not
+    /// handled.
+    #[inline]
+    pub fn lossless(input: &'a str, options: LexerOptions) -> Self {
+        let options = LexerOptions {
+            line_markers: false,
+            ..options
+        };
+        Self {
+            comments: Some(Vec::new()),
+            ..Self::with_options(input, options)
+        }
+    }
+
+    /// Comments lexed since the last call, only kept by
+    /// [`Tokenizer::lossless`]
+    #[inline]
+    pub fn take_comments(&mut self) -> Vec<(Trivia, Span)> {
+        self.comments.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Position right after the last lexed token
+    #[inline]
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// Warnings about tokens lexed so far and trigraphs before them
+    #[inline]
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
+
+    /// Keyword spelled by the source at `span` with lines spliced and
+    /// trigraphs replaced when enabled, `None` if it isn't one
+    #[inline]
+    pub fn keyword_spelling(&self, span: Span) -> Option<&'static str> {
+        let text = &self.source[span.start.offset..span.end.offset];
+        // only words may be keywords
+        if !text.first().is_some_and(|c| c.is_ascii_alphabetic() || *c == b'_') {
+            return None;
+        }
+        let text = source_map::translate(text, self.options.trigraphs).0;
+        str::from_utf8(&text).ok().and_then(identifier::keyword_spelling)
+    }
+
+    /// Lex next token, `None` is returned once the input is exhausted.
+    /// `#line` directives and linemarkers are skipped when enabled and
+    /// lines of tokens and errors after them are the presumed ones.
+    #[inline]
+    pub fn next_token(&mut self) -> Result<Option<SpannedToken>, Error> {
+        loop {
+            let mut token = match self.lex_token() {
+                Ok(Some(token)) => token,
+                Ok(None) => return Ok(None),
+                Err(e) => return Err(self.presumed_error(e)),
+            };
+            let line_marker = self.options.line_markers && !self.skipping;
+            if line_marker && token.line_start && token.token == Token::Hash {
+                // the rest of the line is lexed again if it is not `#line`
+                let saved = (self.c_src, self.pos, self.line_start, self.include);
+                let (warned, released) = (self.warnings.len(), self.trigraphs.released);
+                if self.line_marker(token.span.start)? {
+                    continue;
+                }
+                (self.c_src, self.pos, self.line_start, self.include) = saved;
+                self.warnings.truncate(warned);
+                self.trigraphs.released = released;
+            }
+            for position in [&mut token.span.start, &mut token.span.end] {
+                position.line = self.presumed(position.line);
+            }
+            token.file = self.file.clone();
+            return Ok(Some(token));
+        }
+    }
+
+    #[inline]
+    fn presumed(&self, line: usize) -> usize {
+        (line as isize + self.line_delta) as usize
+    }
+
+    // Error on the presumed line in the presumed file
+    #[inline]
+    fn presumed_error(&self, mut error: Error) -> Error {
+        error.position.line = self.presumed(error.position.line);
+        error.file = self.file.clone();
+        error
+    }
+
+    // Handle the rest of a line starting with `#` if it is `#line` or
+    // linemarker, in which case `true` is returned
+    fn line_marker(&mut self, at: Position) -> Result<bool, Error> {
+        let mut tokens = Vec::new();
+        loop {
+            match self.lex_token() {
+                Ok(Some(token)) if token.token != Token::LineTerminator => tokens.push(token),
+                Ok(_) => break,
+                Err(e) => return Err(self.presumed_error(e)),
+            }
+        }
+        let linemarker = match tokens.first().map(|t| &t.token) {
+            Some(Token::NumericLiteral(_)) => true,
+            Some(Token::Identifier(name)) if &**name == "line" => {
+                tokens.remove(0);
+                false
+            }
+            _ => return Ok(false),
+        };
+        let invalid = self.presumed_error(Error::new(ErrorKind::InvalidLineDirective, at));
+        // linemarkers may have flags after the file name
+        if tokens.len() > 2 && !linemarker {
+            return Err(invalid);
         }
-        let token = &input[c_src - token_len as usize..c_src - 1];
-        let token = unsafe { str::from_utf8_unchecked(token) };
-        let token = super::token::TOKENS
-            .get(token)
-            .cloned()
-            .or_else(|| state_match(st, input, &mut c_src, token_len).unwrap());
-        c_src -= 1;
-        if token.is_some() {
-            tokens.push(token.unwrap());
+        let line = match tokens.first() {
+            Some(number) if matches!(number.token, Token::NumericLiteral(_)) => {
+                let digits = &self.source[number.span.start.offset..number.span.end.offset];
+                str::from_utf8(digits)
+                    .ok()
+                    .filter(|digits| digits.bytes().all(|c| c.is_ascii_digit()))
+                    .and_then(|digits| digits.parse::<usize>().ok())
+                    .ok_or_else(|| invalid.clone())?
+            }
+            _ => return Err(invalid),
+        };
+        match tokens.get(1).map(|t| &t.token) {
+            Some(Token::StringLiteral(literal)) if literal.encoding == Encoding::Plain => {
+                let name = literal.text().ok_or_else(|| invalid.clone())?;
+                self.file = Some(IStr::new(&name));
+            }
+            Some(_) => return Err(invalid),
+            None => {}
+        }
+        self.line_delta = line as isize - self.pos.line as isize;
+        Ok(true)
+    }
+
+    // Lex next token as it is in the source
+    #[inline]
+    fn lex_token(&mut self) -> Result<Option<SpannedToken>, Error> {
+        let input: &[u8] = &self.input;
+        let source = self.source;
+        let separators = self.options.standard >= Standard::C23;
+        while self.c_src < input.len() {
+            // spliced lines before the token are not part of it
+            self.pos.advance(source, self.map.start(self.c_src));
+            if self.include == Include::Directive {
+                if let Some((token, len)) = header_name(&input[self.c_src..]) {
+                    self.c_src += len;
+                    let offset = self.map.start(self.c_src);
+                    self.trigraphs.release(offset, self.line_delta, &mut self.warnings);
+                    return Ok(Some(self.spanned(token)));
+                }
+            }
+            let mut st =
+                StateMachineWrapper::InputElementDiv(StateMachine::<InputElementDiv>::new());
+            let mut c_src = self.c_src;
+            let mut token_len: u64 = 0;
+            while !st.is_final() {
+                // Past the end of input the machine is fed a single virtual
+                // `Eof` character, so tokens at the very end get accepted
+                // without reading out of bounds
+                let e = match input.get(c_src) {
+                    Some(b'\'') if separators && is_digit_separator(&st, input, c_src) => {
+                        Equivalence::DigitSeparator
+                    }
+                    Some(ch) => EQUIVALENCE_CLASS[*ch as usize],
+                    None if c_src == input.len() => Equivalence::Eof,
+                    // only a block comment can still be open at this point
+                    None => return Err(Error::new(ErrorKind::UnterminatedComment, self.pos)),
+                };
+                st = st.step(e);
+                c_src += 1;
+                token_len += 1;
+            }
+            let token = &input[c_src - token_len as usize..c_src - 1];
+            let token = str::from_utf8(token)
+                .ok()
+                .and_then(|token| super::token::TOKENS.get(token));
+            let warned = self.warnings.len();
+            let comment = match st {
+                StateMachineWrapper::SingleLineCommentAcc(_) => Some(Trivia::LineComment),
+                StateMachineWrapper::MultiLineCommentAcc(_) => Some(Trivia::BlockComment),
+                _ => None,
+            };
+            let token = match token {
+                Some(token) => Some(token.clone()),
+                None => match state_match(
+                    st,
+                    input,
+                    &mut c_src,
+                    token_len,
+                    &self.options,
+                    &self.symbols,
+                    &mut self.warnings,
+                ) {
+                    Ok(token) => token,
+                    Err(mut e) => {
+                        // the token is not lexed, neither are its warnings
+                        self.warnings.truncate(warned);
+                        e.position.offset = self.map.start(e.position.offset);
+                        return Err(e.locate(source, self.pos));
+                    }
+                },
+            };
+            for warning in &mut self.warnings[warned..] {
+                warning.position.offset = self.map.start(warning.position.offset);
+                warning.locate(source, self.pos);
+                warning.position.line = (warning.position.line as isize + self.line_delta) as usize;
+            }
+            // the virtual `Eof` character is never part of a token
+            let end = (c_src - 1).min(input.len());
+            // without digraphs only their first character is a token, so
+            // is `:` of `::` before C23
+            let text = &input[self.c_src..end];
+            let split = !self.options.digraphs && is_digraph(text)
+                || self.options.standard < Standard::C23 && text == b"::";
+            let (token, end) = match token {
+                Some(_) if split => {
+                    let first = str::from_utf8(&input[self.c_src..=self.c_src]).ok();
+                    let token = first.and_then(|first| super::token::TOKENS.get(first));
+                    (token.cloned(), self.c_src + 1)
+                }
+                token => (token, end),
+            };
+            let offset = self.map.start(end);
+            self.trigraphs.release(offset, self.line_delta, &mut self.warnings);
+            self.warnings[warned..].sort_by_key(|warning| warning.position.offset);
+            match token {
+                Some(token) if comment.is_some() && self.comments.is_some() => {
+                    // line comment is kept apart from the line terminator
+                    // after it, which is only the newline
+                    let newline = matches!(input[end - 1], b'\n' | b'\r');
+                    let comment_end = if newline { end - 1 } else { end };
+                    let start = self.pos;
+                    self.pos.advance(source, self.map.end(comment_end));
+                    if let Some(comments) = &mut self.comments {
+                        comments.push((Trivia::LineComment, Span::new(start, self.pos)));
+                    }
+                    self.c_src = end;
+                    if newline {
+                        return Ok(Some(self.spanned(token)));
+                    }
+                }
+                Some(token) => {
+                    self.c_src = end;
+                    return Ok(Some(self.spanned(token)));
+                }
+                None => {
+                    // block comment spanning lines separates them
+                    let skipped = &input[self.c_src..end];
+                    if skipped.iter().any(|c| matches!(c, b'\n' | b'\r')) {
+                        self.line_start = true;
+                    }
+                    let start = self.pos;
+                    self.c_src = end;
+                    self.pos.advance(source, self.map.end(end));
+                    if let (Some(comments), Some(comment)) = (&mut self.comments, comment) {
+                        comments.push((comment, Span::new(start, self.pos)));
+                    }
+                }
+            }
+        }
+        self.trigraphs.release(usize::MAX, self.line_delta, &mut self.warnings);
+        Ok(None)
+    }
+
+    // Finish token ending at `c_src`
+    #[inline]
+    fn spanned(&mut self, token: Token) -> SpannedToken {
+        let start = self.pos;
+        self.pos.advance(self.source, self.map.end(self.c_src));
+        let line_start = self.line_start;
+        self.include = self.include.next(&token, line_start);
+        self.line_start = token == Token::LineTerminator;
+        SpannedToken::new(token, Span::new(start, self.pos), line_start)
+    }
+
+    /// Skip the rest of current line, lets lexing go on after an error
+    /// in a line that doesn't have to be lexed correctly
+    #[inline]
+    pub fn skip_line(&mut self) {
+        let input: &[u8] = &self.input;
+        self.c_src += input[self.c_src..]
+            .iter()
+            .position(|c| matches!(c, b'\n' | b'\r'))
+            .unwrap_or(input.len() - self.c_src);
+        self.pos.advance(self.source, self.map.start(self.c_src));
+    }
+
+    /// Symbol table identifiers are classified by
+    #[inline]
+    pub fn symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    /// Symbol table identifiers are classified by, changes affect
+    /// tokens lexed after them
+    #[inline]
+    pub fn symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    /// Set while a group of conditional compilation is skipped, `#line`
+    /// directives and linemarkers in it are lexed as other tokens
+    #[inline]
+    pub fn set_skipping(&mut self, skipping: bool) {
+        self.skipping = skipping;
+    }
+
+    /// [`Token::EOF`] at the end of lexed input, on the presumed line
+    /// and in the presumed file like other tokens
+    #[inline]
+    pub fn eof(&self) -> SpannedToken {
+        let mut end = self.pos;
+        end.line = self.presumed(end.line);
+        SpannedToken {
+            file: self.file.clone(),
+            ..SpannedToken::new(Token::EOF, Span::new(end, end), self.line_start)
         }
+    }
+}
+
+#[inline]
+fn is_digraph(token: &[u8]) -> bool {
+    matches!(token, b"<:" | b":>" | b"<%" | b"%>" | b"%:" | b"%:%:")
+}
 
-        st = StateMachineWrapper::InputElementDiv(StateMachine::<InputElementDiv>::new());
-        token_len = 0;
+// `'` at `at` separates digits of the number being lexed, hexadecimal
+// ones in the digits of hexadecimal constants
+#[inline]
+fn is_digit_separator(st: &StateMachineWrapper, input: &[u8], at: usize) -> bool {
+    let is_digit: fn(&u8) -> bool = match st {
+        StateMachineWrapper::Hex(_) | StateMachineWrapper::HexFraction(_) => u8::is_ascii_hexdigit,
+        StateMachineWrapper::SawZero(_)
+        | StateMachineWrapper::Decimal(_)
+        | StateMachineWrapper::DecimalDigits(_)
+        | StateMachineWrapper::DecimalExponent(_)
+        | StateMachineWrapper::DecimalExponentSigned(_)
+        | StateMachineWrapper::Octal(_)
+        | StateMachineWrapper::HexExponent(_)
+        | StateMachineWrapper::HexExponentSigned(_)
+        | StateMachineWrapper::Binary(_) => u8::is_ascii_digit,
+        _ => return false,
+    };
+    at > 0 && is_digit(&input[at - 1]) && input.get(at + 1).is_some_and(is_digit)
+}
+
+// Header name at the start of `input` with its length, only a complete
+// one on the same line is lexed, otherwise it's lexed as other tokens
+#[inline]
+fn header_name(input: &[u8]) -> Option<(Token, usize)> {
+    let close = match input.first()? {
+        b'<' => b'>',
+        b'"' => b'"',
+        _ => return None,
+    };
+    let len = input[1..]
+        .iter()
+        .take_while(|c| !matches!(c, b'\n' | b'\r'))
+        .position(|c| *c == close)?;
+    let name = str::from_utf8(&input[1..=len]).ok()?.to_owned();
+    let header = HeaderName::new(name, close == b'>');
+    Some((Token::HeaderName(header), len + 2))
+}
+
+#[inline]
+pub fn parse(input: &str, options: LexerOptions) -> Result<Vec<SpannedToken>, Error> {
+    let mut tokenizer = Tokenizer::with_options(input, options);
+    let mut tokens = Vec::new();
+    while let Some(token) = tokenizer.next_token()? {
+        tokens.push(token);
     }
+    tokens.push(tokenizer.eof());
     Ok(tokens)
 }
 
@@ -543,6 +1297,9 @@ fn state_match(
     input: &[u8],
     c_src: &mut usize,
     token_len: u64,
+    options: &LexerOptions,
+    symbols: &SymbolTable,
+    warnings: &mut Vec<Warning>,
 ) -> Result<Option<Token>, Error> {
     let res = match st {
         StateMachineWrapper::LineTerminator(_) => Some(Token::LineTerminator),
@@ -551,49 +1308,101 @@ fn state_match(
         // comment and replace comment with line terminator
         StateMachineWrapper::SingleLineCommentAcc(_) => Some(Token::LineTerminator),
         StateMachineWrapper::MultiLineCommentAcc(_) => None,
-        StateMachineWrapper::SlashAcc(_) =>
-            if input[*c_src - token_len as usize + 1] == b'=' {
+        StateMachineWrapper::SlashAcc(_) => {
+            if input.get(*c_src - token_len as usize + 1) == Some(&b'=') {
                 Some(Token::DivAssign)
             } else {
+                // character after slash is not part of the token
+                *c_src -= 1;
                 Some(Token::Slash)
-            },
-        StateMachineWrapper::String(_) => Some(string::parse_string(input, c_src)),
-        StateMachineWrapper::Char(_) => Some(string::parse_char(input, c_src)),
-        StateMachineWrapper::BinaryAcc(_) => Some(parse_number_radix(input, c_src, token_len, 2)?),
-        StateMachineWrapper::OctalAcc(_) => Some(parse_number_radix(input, c_src, token_len, 8)?),
-        StateMachineWrapper::HexAcc(_) => Some(parse_number_radix(input, c_src, token_len, 16)?),
-        StateMachineWrapper::DecimalAcc(_) => Some(parse_number(input, c_src, token_len)?),
-        StateMachineWrapper::DotPart(_) => Some(parse_dot(input, c_src)),
-        StateMachineWrapper::DecimalDigitsAcc(_) => {
-            Some(parse_number_decimal(input, c_src, token_len)?)
+            }
+        }
+        StateMachineWrapper::String(_) => {
+            Some(string::parse_string(input, c_src, Encoding::Plain, options)?)
+        }
+        StateMachineWrapper::Char(_) => {
+            Some(string::parse_char(input, c_src, Encoding::Plain, options, warnings)?)
         }
-        StateMachineWrapper::DecimalExponentSignedAcc(_) => {
-            Some(parse_exponent(input, c_src, token_len)?)
+        StateMachineWrapper::HELL(_) => {
+            let start = *c_src - token_len as usize;
+            let c = str::from_utf8(&input[start..])
+                .ok()
+                .and_then(|rest| rest.chars().next())
+                .unwrap_or(char::REPLACEMENT_CHARACTER);
+            return Err(Error::at(ErrorKind::StrayCharacter(c), start));
         }
-        StateMachineWrapper::DecimalExponentAcc(_) => {
-            Some(parse_exponent(input, c_src, token_len)?)
+        StateMachineWrapper::BinaryAcc(_)
+        | StateMachineWrapper::OctalAcc(_)
+        | StateMachineWrapper::HexAcc(_)
+        | StateMachineWrapper::DecimalAcc(_)
+        | StateMachineWrapper::IntegerSuffixAcc(_) => {
+            Some(parse_integer(input, c_src, token_len, options)?)
         }
-        StateMachineWrapper::Identifier(_) => Some(identifier::parse_identifier(input, c_src)),
+        // third dot is the last character of the token, not a lookahead
+        StateMachineWrapper::EllipsisAcc(_) => Some(Token::ELLIPSIS),
+        StateMachineWrapper::DecimalDigitsAcc(_)
+        | StateMachineWrapper::DecimalExponentAcc(_)
+        | StateMachineWrapper::DecimalExponentSignedAcc(_)
+        | StateMachineWrapper::HexFractionAcc(_)
+        | StateMachineWrapper::HexExponentAcc(_)
+        | StateMachineWrapper::HexExponentSignedAcc(_)
+        | StateMachineWrapper::FloatSuffixAcc(_) => Some(parse_float(input, c_src, token_len)?),
+        StateMachineWrapper::Identifier(_) => {
+            let start = *c_src - 1;
+            let token = identifier::parse_identifier(input, c_src, options, symbols);
+            // identifier right before quote may be encoding prefix
+            let quote = input.get(*c_src).copied().unwrap_or_default();
+            match string::encoding_prefix(&input[start..*c_src], quote, options) {
+                Some(encoding) if quote == b'"' => {
+                    *c_src += 1;
+                    Some(string::parse_string(input, c_src, encoding, options)?)
+                }
+                Some(encoding) if quote == b'\'' => {
+                    *c_src += 1;
+                    let token = string::parse_char(input, c_src, encoding, options, warnings)?;
+                    Some(token)
+                }
+                _ => Some(token),
+            }
+        }
+        StateMachineWrapper::LtAcc(_)
+        | StateMachineWrapper::GtAcc(_)
+        | StateMachineWrapper::AssignAcc(_)
+        | StateMachineWrapper::ExclamationAcc(_)
+        | StateMachineWrapper::PlusAcc(_)
+        | StateMachineWrapper::MinusAcc(_)
+        | StateMachineWrapper::MultiAcc(_)
+        | StateMachineWrapper::ModAcc(_)
+        | StateMachineWrapper::AndAcc(_)
+        | StateMachineWrapper::OrAcc(_)
+        | StateMachineWrapper::ExclusiveOrAcc(_)
+        | StateMachineWrapper::ColonAcc(_)
+        | StateMachineWrapper::HashAcc(_)
+        | StateMachineWrapper::DotPart(_) => parse_punctuator(input, c_src, token_len),
         _ => None,
     };
     *c_src += 1;
     Ok(res)
 }
 
+// Punctuator states loop on any operator characters, so the accepted
+// text may be longer than any known punctuator (`+++`, `->>`, `..`). Take the
+// longest known prefix and leave the rest for the next token.
 #[inline]
-fn parse_dot(input: &[u8], c_src: &mut usize) -> Token {
-    let rest_len = input.len() - *c_src;
-    if rest_len >= 2 && input[*c_src] == b'.' && input[*c_src + 1] == b'.' {
-        *c_src += 2;
-        return Token::ELLIPSIS;
-    }
-
-    Token::Dot
+fn parse_punctuator(input: &[u8], c_src: &mut usize, token_len: u64) -> Option<Token> {
+    let start = *c_src - token_len as usize;
+    let text = str::from_utf8(&input[start..*c_src - 1]).ok()?;
+    (1..=text.len()).rev().find_map(|len| {
+        let token = super::token::TOKENS.get(&text[..len])?;
+        *c_src = start + len;
+        Some(token.clone())
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::*;
+    use ::internship::IStr;
 
     should!(
         lineterminator_all,
@@ -735,6 +1544,512 @@ mod tests {
 
     should!(comma, ", ", vec![Token::Comma, Token::EOF]);
 
-    // should_fail!(string_single, "'cau\n'",
-    // vec![Token::StringLiteral(String::from("cau")), Token::EOF]);
-}
\ No newline at end of file
+    should!(
+        plus_triple,
+        "a+++b",
+        vec![
+            Token::Identifier(IStr::new("a")),
+            Token::IncOp,
+            Token::Plus,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        ptr_op_shift,
+        "->>",
+        vec![Token::PtrOp, Token::Gt, Token::EOF]
+    );
+
+    should!(
+        slash_between,
+        "a/b",
+        vec![
+            Token::Identifier(IStr::new("a")),
+            Token::Slash,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        identifier_at_eof,
+        "abc",
+        vec![Token::Identifier(IStr::new("abc")), Token::EOF]
+    );
+
+    should!(single_comment_at_eof, "// rest", vec![Token::LineTerminator, Token::EOF]);
+
+    should_fail!(
+        string_single,
+        "'cau\n'",
+        Error::new(ErrorKind::UnterminatedChar, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        multi_comment_unterminated,
+        "a\n /* b",
+        Error::new(ErrorKind::UnterminatedComment, Position::new(3, 2, 2))
+    );
+
+    should_fail!(
+        stray_character,
+        "a = `b`;",
+        Error::new(ErrorKind::StrayCharacter('`'), Position::new(4, 1, 5))
+    );
+
+    should_fail!(
+        stray_unicode,
+        "int é;",
+        Error::new(ErrorKind::StrayCharacter('é'), Position::new(4, 1, 5))
+    );
+
+    use crate::{
+        error::{Error, ErrorKind, Warning},
+        span::{Position, Span},
+    };
+
+    #[test]
+    fn spans() {
+        let tokens = Lexer::lex_spanned("a +=\n  10;").unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(Position::new(0, 1, 1), Position::new(1, 1, 2)),
+                Span::new(Position::new(2, 1, 3), Position::new(4, 1, 5)),
+                Span::new(Position::new(4, 1, 5), Position::new(5, 2, 1)),
+                Span::new(Position::new(7, 2, 3), Position::new(9, 2, 5)),
+                Span::new(Position::new(9, 2, 5), Position::new(10, 2, 6)),
+                Span::new(Position::new(10, 2, 6), Position::new(10, 2, 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn spans_crlf_and_comments() {
+        let tokens = Lexer::lex_spanned("/* a\r\n b */ x // c\r\ny").unwrap();
+        let tokens: Vec<(Token, Position)> =
+            tokens.into_iter().map(|t| (t.token, t.span.start)).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Identifier(IStr::new("x")), Position::new(12, 2, 7)),
+                (Token::LineTerminator, Position::new(14, 2, 9)),
+                (Token::LineTerminator, Position::new(19, 3, 1)),
+                (Token::Identifier(IStr::new("y")), Position::new(20, 3, 1)),
+                (Token::EOF, Position::new(21, 3, 2)),
+            ]
+        );
+    }
+
+    should!(
+        splice_inside_tokens,
+        "ab\\\ncd +\\\r\n= 1\\\n2 \"x\\\ny\"",
+        vec![
+            Token::Identifier(IStr::new("abcd")),
+            Token::AddAssign,
+            Token::NumericLiteral(Number::Integer(Integer::new(12, 10, IntegerSuffix::default()))),
+            Token::StringLiteral(StringLiteral::new(
+                String::from("\"xy\""),
+                Encoding::Plain,
+                CodeUnits::U8(b"xy".to_vec())
+            )),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        splice_line_comment,
+        "// comment \\\n still comment\nx /\\\n* block *\\\n/ y",
+        vec![
+            Token::LineTerminator,
+            Token::Identifier(IStr::new("x")),
+            Token::Identifier(IStr::new("y")),
+            Token::EOF
+        ]
+    );
+
+    #[test]
+    fn splice_spans() {
+        let tokens = Lexer::lex_spanned("ab\\\ncd \\\n  e\\\n").unwrap();
+        let spans: Vec<Span> = tokens.iter().map(|t| t.span).collect();
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(Position::new(0, 1, 1), Position::new(6, 2, 3)),
+                Span::new(Position::new(11, 3, 3), Position::new(12, 3, 4)),
+                Span::new(Position::new(12, 3, 4), Position::new(12, 3, 4)),
+            ]
+        );
+    }
+
+    should_fail!(
+        splice_error_position,
+        "x\\\n  @",
+        Error::new(ErrorKind::StrayCharacter('@'), Position::new(5, 2, 3))
+    );
+
+    should!(
+        digraphs,
+        "<: :> <% %> %: %:%: <::> %:% a<:1:>",
+        vec![
+            Token::LBracket,
+            Token::RBracket,
+            Token::LBrace,
+            Token::RBrace,
+            Token::Hash,
+            Token::HashHash,
+            Token::LBracket,
+            Token::RBracket,
+            Token::Hash,
+            Token::Mod,
+            Token::Identifier(IStr::new("a")),
+            Token::LBracket,
+            Token::NumericLiteral(Number::Integer(Integer::new(1, 10, IntegerSuffix::default()))),
+            Token::RBracket,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        hashes,
+        "# ## ### %:# #%: a##b",
+        vec![
+            Token::Hash,
+            Token::HashHash,
+            Token::HashHash,
+            Token::Hash,
+            Token::Hash,
+            Token::Hash,
+            Token::Hash,
+            Token::Hash,
+            Token::Identifier(IStr::new("a")),
+            Token::HashHash,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    fn header(name: &str, system: bool) -> Token {
+        Token::HeaderName(HeaderName::new(name.to_string(), system))
+    }
+
+    should!(
+        header_names,
+        "#include <stdio.h>\n  # /* */ include \"dir/a b.h\" // c\n%:include_next<x\\y>",
+        vec![
+            Token::Hash,
+            Token::Identifier(IStr::new("include")),
+            header("stdio.h", true),
+            Token::LineTerminator,
+            Token::Hash,
+            Token::Identifier(IStr::new("include")),
+            header("dir/a b.h", false),
+            Token::LineTerminator,
+            Token::Hash,
+            Token::Identifier(IStr::new("include_next")),
+            header("x\\y", true),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        header_names_outside_include,
+        "x #include <a>\n#define y <b>\n#include <c\n>",
+        vec![
+            Token::Identifier(IStr::new("x")),
+            Token::Hash,
+            Token::Identifier(IStr::new("include")),
+            Token::Lt,
+            Token::Identifier(IStr::new("a")),
+            Token::Gt,
+            Token::LineTerminator,
+            Token::Hash,
+            Token::Identifier(IStr::new("define")),
+            Token::Identifier(IStr::new("y")),
+            Token::Lt,
+            Token::Identifier(IStr::new("b")),
+            Token::Gt,
+            Token::LineTerminator,
+            Token::Hash,
+            Token::Identifier(IStr::new("include")),
+            Token::Lt,
+            Token::Identifier(IStr::new("c")),
+            Token::LineTerminator,
+            Token::Gt,
+            Token::EOF
+        ]
+    );
+
+    #[test]
+    fn line_start() {
+        let tokens = Lexer::lex_spanned("#if 1 /* a\n */ # x\n  y /* */ #\n").unwrap();
+        let starts: Vec<(Token, bool)> =
+            tokens.into_iter().map(|t| (t.token, t.line_start)).collect();
+        assert_eq!(
+            starts,
+            vec![
+                (Token::Hash, true),
+                (Token::IF, false),
+                (
+                    Token::NumericLiteral(Number::Integer(Integer::new(
+                        1,
+                        10,
+                        IntegerSuffix::default()
+                    ))),
+                    false
+                ),
+                (Token::Hash, true),
+                (Token::Identifier(IStr::new("x")), false),
+                (Token::LineTerminator, false),
+                (Token::Identifier(IStr::new("y")), true),
+                (Token::Hash, false),
+                (Token::LineTerminator, false),
+                (Token::EOF, true),
+            ]
+        );
+    }
+
+    fn trigraphs() -> crate::options::LexerOptions {
+        crate::options::LexerOptions {
+            trigraphs: true,
+            ..Default::default()
+        }
+    }
+
+    should!(
+        trigraphs_replaced,
+        "??=??( ??) ??< ??> ??' ??! ??- a??/\nb",
+        trigraphs(),
+        vec![
+            Token::Hash,
+            Token::LBracket,
+            Token::RBracket,
+            Token::LBrace,
+            Token::RBrace,
+            Token::ExclusiveOr,
+            Token::InclusiveOr,
+            Token::Tilde,
+            Token::Identifier(IStr::new("ab")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        digraphs_disabled,
+        "<: %> %:%:",
+        crate::options::LexerOptions {
+            digraphs: false,
+            ..Default::default()
+        },
+        vec![
+            Token::Lt,
+            Token::Colon,
+            Token::Mod,
+            Token::Gt,
+            Token::Mod,
+            Token::Colon,
+            Token::Mod,
+            Token::Colon,
+            Token::EOF
+        ]
+    );
+
+    should!(
+        digraphs_c95,
+        "<: %>",
+        crate::options::LexerOptions::for_standard(crate::options::Standard::C95),
+        vec![Token::LBracket, Token::RBrace, Token::EOF]
+    );
+
+    should!(
+        attributes_c23,
+        "[[gnu::unused, deprecated]] a:::b",
+        crate::options::LexerOptions::for_standard(crate::options::Standard::C23),
+        vec![
+            Token::LBracket,
+            Token::LBracket,
+            Token::Identifier(IStr::new("gnu")),
+            Token::ColonColon,
+            Token::Identifier(IStr::new("unused")),
+            Token::Comma,
+            Token::Identifier(IStr::new("deprecated")),
+            Token::RBracket,
+            Token::RBracket,
+            Token::Identifier(IStr::new("a")),
+            Token::ColonColon,
+            Token::Colon,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        colons_before_c23,
+        "a::b",
+        vec![
+            Token::Identifier(IStr::new("a")),
+            Token::Colon,
+            Token::Colon,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        trigraphs_ignored,
+        "a ??- b",
+        vec![
+            Token::Identifier(IStr::new("a")),
+            Token::QuestionMark,
+            Token::QuestionMark,
+            Token::Minus,
+            Token::Identifier(IStr::new("b")),
+            Token::EOF
+        ]
+    );
+
+    #[test]
+    fn trigraph_warnings() {
+        let mut lexer = Lexer::with_options("x\n ??<??>", trigraphs());
+        let tokens: Vec<_> = lexer.by_ref().map(|t| t.unwrap()).collect();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Identifier(IStr::new("x")),
+                Token::LineTerminator,
+                Token::LBrace,
+                Token::RBrace
+            ]
+        );
+        assert_eq!(
+            lexer.warnings(),
+            &[
+                Warning::new(ErrorKind::Trigraph('<'), Position::new(3, 2, 2)),
+                Warning::new(ErrorKind::Trigraph('>'), Position::new(6, 2, 5)),
+            ]
+        );
+        assert_eq!(
+            lexer.warnings()[0].to_string(),
+            "2:2: warning: trigraph `??<` converted to `{`"
+        );
+        let mut lexer = Lexer::new("\"??=\"");
+        assert_eq!(lexer.by_ref().count(), 1);
+        assert_eq!(
+            lexer.warnings(),
+            &[Warning::new(ErrorKind::IgnoredTrigraph('='), Position::new(1, 1, 2))]
+        );
+        // trigraphs are warned about once lexing gets past them
+        let options = crate::options::LexerOptions {
+            line_markers: true,
+            ..trigraphs()
+        };
+        let mut lexer = Lexer::with_options("x ??<\n#line 10\n??>", options);
+        assert_eq!(lexer.next(), Some(Ok(Token::Identifier(IStr::new("x")))));
+        assert!(lexer.warnings().is_empty());
+        assert_eq!(lexer.by_ref().count(), 3);
+        assert_eq!(
+            lexer.warnings(),
+            &[
+                Warning::new(ErrorKind::Trigraph('<'), Position::new(2, 1, 3)),
+                Warning::new(ErrorKind::Trigraph('>'), Position::new(15, 10, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn trigraph_spans() {
+        let mut lexer = Lexer::with_options("??(x ??/\ny??>", trigraphs());
+        let mut spans = Vec::new();
+        while let Some(t) = lexer.next_spanned() {
+            let span = t.unwrap().span;
+            spans.push((span.start.offset, span.end.offset));
+        }
+        assert_eq!(spans, vec![(0, 3), (3, 4), (9, 10), (10, 13)]);
+    }
+
+    fn line_markers() -> crate::options::LexerOptions {
+        crate::options::LexerOptions {
+            line_markers: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn line_directives() {
+        let input = "a\n# 42 \"foo.c\" 1 3\nb\n#line 7\n\nc\n#line 1 \"C:\\\\x.h\"\n# define d\n";
+        let mut lexer = Lexer::with_options(input, line_markers());
+        let tokens: Vec<(Token, usize, Option<IStr>)> = std::iter::from_fn(|| lexer.next_spanned())
+            .map(|t| t.unwrap())
+            .filter(|t| t.token != Token::LineTerminator)
+            .map(|t| (t.token, t.span.start.line, t.file))
+            .collect();
+        let foo = Some(IStr::new("foo.c"));
+        let x = Some(IStr::new("C:\\x.h"));
+        assert_eq!(
+            tokens,
+            vec![
+                (Token::Identifier(IStr::new("a")), 1, None),
+                (Token::Identifier(IStr::new("b")), 42, foo.clone()),
+                (Token::Identifier(IStr::new("c")), 8, foo),
+                (Token::Hash, 1, x.clone()),
+                (Token::Identifier(IStr::new("define")), 1, x.clone()),
+                (Token::Identifier(IStr::new("d")), 1, x),
+            ]
+        );
+        let tokens = Lexer::lex("#line 7\n").unwrap();
+        assert_eq!(tokens[0], Token::Hash);
+        // end of input is in the presumed file too
+        let input = "# 42 \"foo.c\"\nx\ny";
+        let defined = std::collections::HashSet::new();
+        let eof = [
+            Lexer::lex_spanned_with(input, line_markers()).unwrap().pop(),
+            Lexer::lex_active_with(input, &defined, line_markers())
+                .unwrap()
+                .tokens
+                .pop(),
+        ];
+        for eof in eof.iter().flatten() {
+            assert_eq!(eof.token, Token::EOF);
+            assert_eq!((eof.span.start.line, eof.span.start.column), (43, 2));
+            assert_eq!(eof.file, Some(IStr::new("foo.c")));
+        }
+    }
+
+    #[test]
+    fn line_directive_errors() {
+        let mut lexer = Lexer::with_options("#line 10\n\n@", line_markers());
+        assert_eq!(lexer.next(), Some(Ok(Token::LineTerminator)));
+        assert_eq!(
+            lexer.next(),
+            Some(Err(Error::new(
+                ErrorKind::StrayCharacter('@'),
+                Position::new(10, 11, 1)
+            )))
+        );
+        let mut lexer = Lexer::with_options("# 5 \"foo.c\"\n@", line_markers());
+        let error = lexer.next().unwrap().unwrap_err();
+        assert_eq!(error.position.line, 5);
+        assert_eq!(error.file, Some(IStr::new("foo.c")));
+        assert!(error.to_string().starts_with("foo.c:5:1: "));
+        for input in &[
+            "#line x\n",
+            "#line 0x10\n",
+            "#line 1 \"a\" 2\n",
+            "#line 1 L\"a\"",
+        ] {
+            let mut lexer = Lexer::with_options(input, line_markers());
+            assert_eq!(
+                lexer.next(),
+                Some(Err(Error::new(
+                    ErrorKind::InvalidLineDirective,
+                    Position::new(0, 1, 1)
+                ))),
+                "input {:?}",
+                input
+            );
+        }
+    }
+}
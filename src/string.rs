@@ -1,145 +1,799 @@
-use crate::token::{Token, Number};
+use crate::{
+    error::{Error, ErrorKind, Warning},
+    options::{LexerOptions, MultiChar, Standard},
+    token::{CharLiteral, CodeUnits, Encoding, StringLiteral, Token},
+};
 use std::{char::from_u32, str};
 
-// # Performance
-// If string doesn't contain any escaping characters we can skip
-// allocation entirely. This is achieved by allocating new string
-// of size of input only after escaping character is encountered
+/// Piece of decoded literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Element {
+    /// Source character or universal character name, to be encoded
+    Char(char),
+    /// Single code unit given by octal or hexadecimal escape
+    Unit(u32),
+}
+
+// Reads `\u` or `\U` universal character name starting at `start`
+// followed by exactly `digits` hexadecimal digits. Before C23 characters
+// below U+00A0 other than `$`, `@` and `` ` `` can't be named (C11
+// 6.4.3p2), literals may name any character since.
 #[inline]
-fn to_unescaped(input: String) -> String {
-    let mut result: Option<String> = None;
-    let bytes = input.as_bytes();
-    let mut escaping = false;
-    let mut i = 0..bytes.len();
-    loop {
-        let next = i.next();
-        if next == None {
-            break;
-        }
-        let c = unsafe { bytes.get_unchecked(next.unwrap()) };
-        if *c == b'\\' {
-            escaping = true;
-            if result.is_none() {
-                result = Some(String::from(&input[..next.unwrap()]));
-                result.as_mut().unwrap().reserve(input.len());
-            }
+fn universal_char(
+    input: &str,
+    start: usize,
+    digits: usize,
+    standard: Standard,
+) -> Result<(Element, usize), Error> {
+    let basic = standard >= Standard::C23;
+    let len = 2 + digits;
+    input
+        .get(start + 2..start + len)
+        .filter(|nums| nums.bytes().all(|b| b.is_ascii_hexdigit()))
+        .and_then(|nums| u32::from_str_radix(nums, 16).ok())
+        .filter(|value| basic || *value >= 0xA0 || matches!(value, 0x24 | 0x40 | 0x60))
+        .and_then(from_u32)
+        .map(|c| (Element::Char(c), len))
+        .ok_or_else(|| {
+            let escape = input[start..].chars().take(len).collect();
+            Error::at(ErrorKind::InvalidEscape(escape), start)
+        })
+}
+
+// Reads octal (up to 3 digits) or hexadecimal (any number of digits)
+// escape starting at `start`, its value has to fit `max`
+#[inline]
+fn numeric_escape(
+    input: &str,
+    start: usize,
+    radix: u32,
+    max: u32,
+) -> Result<(Element, usize), Error> {
+    let (digits_start, max_digits) = if radix == 8 { (1, 3) } else { (2, usize::MAX) };
+    let digits = input[start + digits_start..]
+        .bytes()
+        .take(max_digits)
+        .take_while(|b| char::from(*b).is_digit(radix))
+        .count();
+    let len = digits_start + digits;
+    let escape = &input[start..start + len];
+    if digits == 0 {
+        return Err(Error::at(ErrorKind::InvalidEscape(escape.to_string()), start));
+    }
+    u32::from_str_radix(&escape[digits_start..], radix)
+        .ok()
+        .filter(|value| *value <= max)
+        .map(|value| (Element::Unit(value), len))
+        .ok_or_else(|| Error::at(ErrorKind::EscapeOutOfRange(escape.to_string()), start))
+}
+
+// Decodes contents of a literal between quotes, `max` is the largest
+// code unit octal and hexadecimal escapes may produce. Error offsets
+// are relative to the contents.
+#[inline]
+fn decode(input: &str, max: u32, standard: Standard) -> Result<Vec<Element>, Error> {
+    let mut result = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while let Some(c) = input[i..].chars().next() {
+        if c != '\\' {
+            result.push(Element::Char(c));
+            i += c.len_utf8();
             continue;
         }
-        if escaping {
-            escaping = false;
-            let res = match *c as char {
-                'n' => '\n',
-                'r' => '\r',
-                't' => '\t',
-                'b' => '\x08',
-                'v' => '\x0B',
-                'f' => '\x0C',
-                '0' => '\0',
-                'u' => {
-                    let index = i.next().unwrap();
-                    let nums = &bytes[index..index + 4];
-                    for _ in 0..3 {
-                        i.next();
-                    }
-                    let as_num = u64::from_str_radix(unsafe { str::from_utf8_unchecked(nums) }, 16).unwrap_or(0);
-                    from_u32(as_num as u32).expect(format!("{} is not a valid unicode scalar value", as_num).as_str())
-                }
-                'x' => {
-                    let index = i.next().unwrap();
-                    let nums = &bytes[index..index + 2];
-                    for _ in 0..3 {
-                        i.next();
+        let simple = |c| Ok((Element::Char(c), 2));
+        let (element, len) = match input[i + 1..].chars().next() {
+            Some(c @ '\'') | Some(c @ '"') | Some(c @ '?') | Some(c @ '\\') => simple(c),
+            Some('a') => simple('\x07'),
+            Some('b') => simple('\x08'),
+            Some('f') => simple('\x0C'),
+            Some('n') => simple('\n'),
+            Some('r') => simple('\r'),
+            Some('t') => simple('\t'),
+            Some('v') => simple('\x0B'),
+            Some('0'..='7') => numeric_escape(input, i, 8, max),
+            Some('x') => numeric_escape(input, i, 16, max),
+            Some('u') => universal_char(input, i, 4, standard),
+            Some('U') => universal_char(input, i, 8, standard),
+            Some(c) => {
+                let escape = format!("\\{}", c);
+                Err(Error::at(ErrorKind::InvalidEscape(escape), i))
+            }
+            None => {
+                let escape = String::from("\\");
+                Err(Error::at(ErrorKind::InvalidEscape(escape), i))
+            }
+        }?;
+        result.push(element);
+        i += len;
+    }
+    Ok(result)
+}
+
+// Width of code units of the encoding in bits
+#[inline]
+fn unit_bits(encoding: Encoding, options: &LexerOptions) -> u32 {
+    match encoding {
+        Encoding::Plain | Encoding::Utf8 => 8,
+        Encoding::Utf16 => 16,
+        Encoding::Wide if options.short_wchar => 16,
+        Encoding::Utf32 | Encoding::Wide => 32,
+    }
+}
+
+// Encodes decoded literal to code units `bits` wide, characters in
+// UTF-8, UTF-16 or UTF-32 and escapes to single units
+#[inline]
+fn encode(elements: &[Element], bits: u32) -> CodeUnits {
+    match bits {
+        8 => {
+            let mut units = Vec::with_capacity(elements.len());
+            for element in elements {
+                match *element {
+                    Element::Char(c) => {
+                        units.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes())
                     }
-                    let as_num = u64::from_str_radix(unsafe { str::from_utf8_unchecked(nums) }, 16).unwrap_or(0);
-                    from_u32(as_num as u32).expect(format!("{} is not a valid unicode scalar value", as_num).as_str())
+                    Element::Unit(unit) => units.push(unit as u8),
                 }
-                _ => *c as char,
-            };
-            result.as_mut().unwrap().push(res);
-            continue;
+            }
+            CodeUnits::U8(units)
         }
-        if result.is_some() {
-            result.as_mut().unwrap().push(*c as char);
+        16 => {
+            let mut units = Vec::with_capacity(elements.len());
+            for element in elements {
+                match *element {
+                    Element::Char(c) => units.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+                    Element::Unit(unit) => units.push(unit as u16),
+                }
+            }
+            CodeUnits::U16(units)
         }
+        _ => CodeUnits::U32(
+            elements
+                .iter()
+                .map(|element| match *element {
+                    Element::Char(c) => u32::from(c),
+                    Element::Unit(unit) => unit,
+                })
+                .collect(),
+        ),
     }
-    result.unwrap_or(input)
 }
 
+/// Encoding selected by prefix of string literal, `u8` is not allowed
+/// on character constants before C23. Only `L` is known before C11.
 #[inline]
-fn parse(input: &[u8], c_src: &mut usize, type_: u8) -> String {
-    let mut token_len = 0;
-    while input.len() - 1 > *c_src && (input[*c_src] != type_ || input[*c_src - 1] == b'\\') {
-        *c_src += 1;
-        token_len += 1;
-    }
-    let res = unsafe { str::from_utf8_unchecked(&input[*c_src - token_len..*c_src]).to_string() };
-    let res = to_unescaped(res);
+pub fn encoding_prefix(prefix: &[u8], quote: u8, options: &LexerOptions) -> Option<Encoding> {
+    let unicode = options.standard >= Standard::C11;
+    match (prefix, quote) {
+        (b"u8", b'"') if unicode => Some(Encoding::Utf8),
+        (b"u8", b'\'') if options.standard >= Standard::C23 => Some(Encoding::Utf8),
+        (b"u", _) if unicode => Some(Encoding::Utf16),
+        (b"U", _) if unicode => Some(Encoding::Utf32),
+        (b"L", _) => Some(Encoding::Wide),
+        _ => None,
+    }
+}
+
+// Decodes contents of literal of given encoding, `start` is the offset
+// of its opening quote
+#[inline]
+fn decode_literal(
+    input: &[u8],
+    c_src: &mut usize,
+    encoding: Encoding,
+    options: &LexerOptions,
+    type_: u8,
+    unterminated: ErrorKind,
+) -> Result<(String, CodeUnits), Error> {
+    let quote = *c_src - 1;
+    let start = quote - encoding.prefix().len();
+    let contents = parse(input, c_src, start, type_, unterminated)?;
+    let bits = unit_bits(encoding, options);
+    let max = u32::MAX >> (32 - bits);
+    let value = decode(contents, max, options.standard).map_err(|e| e.shift(quote + 1))?;
+    let raw = String::from_utf8_lossy(&input[start..*c_src]).into_owned();
+    Ok((raw, encode(&value, bits)))
+}
+
+/// Decode string literal again as if it had `encoding` prefix, used
+/// when concatenation with a prefixed literal promotes it. Error offsets
+/// are relative to the contents of the literal.
+#[inline]
+pub fn reencode(
+    literal: &StringLiteral,
+    encoding: Encoding,
+    options: &LexerOptions,
+) -> Result<CodeUnits, Error> {
+    let raw = &literal.raw;
+    let contents = &raw[literal.encoding.prefix().len() + 1..raw.len() - 1];
+    let bits = unit_bits(encoding, options);
+    let value = decode(contents, u32::MAX >> (32 - bits), options.standard)?;
+    Ok(encode(&value, bits))
+}
+
+// Finds the closing quote of literal, `c_src` points just past the
+// opening one and is left just past the closing one. Returns contents
+// between the quotes, unterminated literal is reported at `literal`.
+#[inline]
+fn parse<'a>(
+    input: &'a [u8],
+    c_src: &mut usize,
+    literal: usize,
+    type_: u8,
+    unterminated: ErrorKind,
+) -> Result<&'a str, Error> {
+    let start = *c_src;
+    loop {
+        match input.get(*c_src) {
+            // escaped character can't terminate the literal
+            Some(b'\\') => *c_src += 2,
+            Some(b'\n') | Some(b'\r') | None => return Err(Error::at(unterminated, literal)),
+            Some(c) if *c == type_ => break,
+            Some(_) => *c_src += 1,
+        }
+    }
+    let res = str::from_utf8(&input[start..*c_src]).unwrap_or_default();
     *c_src += 1;
-    res
+    Ok(res)
 }
 
+/// Parse string literal, `c_src` points just past its opening quote
 #[inline]
-pub fn parse_string(input: &[u8], c_src: &mut usize) -> Token {
-    Token::StringLiteral(parse(input, c_src, b'"'))
+pub fn parse_string(
+    input: &[u8],
+    c_src: &mut usize,
+    encoding: Encoding,
+    options: &LexerOptions,
+) -> Result<Token, Error> {
+    let (raw, value) = decode_literal(
+        input,
+        c_src,
+        encoding,
+        options,
+        b'"',
+        ErrorKind::UnterminatedString,
+    )?;
+    Ok(Token::StringLiteral(StringLiteral::new(raw, encoding, value)))
+}
+
+// Value of plain character constant, multi-character constants are
+// packed into `int` from the left
+#[inline]
+fn plain_char_value(
+    units: &CodeUnits,
+    start: usize,
+    options: &LexerOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<i64, Error> {
+    let len = units.len();
+    let first = units.get(0).unwrap_or_default();
+    if len == 1 {
+        return Ok(if options.signed_char {
+            i64::from(first as u8 as i8)
+        } else {
+            i64::from(first)
+        });
+    }
+    if options.multichar == MultiChar::Reject {
+        return Err(Error::at(ErrorKind::MultiCharConstant, start));
+    }
+    let kind = if len > 4 {
+        ErrorKind::CharConstantTooLong
+    } else {
+        ErrorKind::MultiCharConstant
+    };
+    warnings.push(Warning::at(kind, start));
+    let packed = (0..len).fold(0u32, |packed, i| {
+        packed.wrapping_shl(8) | units.get(i).unwrap_or_default()
+    });
+    Ok(i64::from(packed as i32))
 }
 
+/// Parse character constant, `c_src` points just past its opening quote
 #[inline]
-pub fn parse_char(input: &[u8], c_src: &mut usize) -> Token {
-    let res = parse(input, c_src, b'\'');
-    debug_assert_eq!(1, res.len());
-    Token::NumericLiteral(Number::new(res.chars().next().unwrap() as u32, 0, 0, 10))
+pub fn parse_char(
+    input: &[u8],
+    c_src: &mut usize,
+    encoding: Encoding,
+    options: &LexerOptions,
+    warnings: &mut Vec<Warning>,
+) -> Result<Token, Error> {
+    let start = *c_src - 1 - encoding.prefix().len();
+    let (raw, units) = decode_literal(
+        input,
+        c_src,
+        encoding,
+        options,
+        b'\'',
+        ErrorKind::UnterminatedChar,
+    )?;
+    if units.is_empty() {
+        return Err(Error::at(ErrorKind::EmptyCharConstant, start));
+    }
+    let last = units.get(units.len() - 1).unwrap_or_default();
+    let value = match encoding {
+        Encoding::Plain => plain_char_value(&units, start, options, warnings)?,
+        // UTF constants have to fit a single code unit
+        Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32 if units.len() > 1 => {
+            return Err(Error::at(ErrorKind::CharConstantTooLong, start));
+        }
+        Encoding::Utf8 | Encoding::Utf16 | Encoding::Utf32 => i64::from(last),
+        // wide constants take the last character like GCC does
+        Encoding::Wide => {
+            if units.len() > 1 {
+                warnings.push(Warning::at(ErrorKind::CharConstantTooLong, start));
+            }
+            if options.short_wchar {
+                i64::from(last)
+            } else {
+                i64::from(last as i32)
+            }
+        }
+    };
+    Ok(Token::CharLiteral(CharLiteral::new(raw, encoding, units, value)))
 }
 
 #[cfg(test)]
 mod tests {
     use super::super::*;
+    use crate::{
+        error::{Error, ErrorKind, Warning},
+        options::{LexerOptions, MultiChar, Standard},
+        span::Position,
+    };
+    use ::internship::IStr;
+
+    fn string(raw: &str, value: &[u8]) -> Token {
+        Token::StringLiteral(StringLiteral::new(
+            raw.to_string(),
+            Encoding::Plain,
+            CodeUnits::U8(value.to_vec()),
+        ))
+    }
+
+    fn chr(raw: &str, value: u8) -> Token {
+        Token::CharLiteral(CharLiteral::new(
+            raw.to_string(),
+            Encoding::Plain,
+            CodeUnits::U8(vec![value]),
+            i64::from(value as i8),
+        ))
+    }
 
     should!(
         string_single,
         "\"cau\"",
-        vec![Token::StringLiteral(String::from("cau")), Token::EOF]
+        vec![string("\"cau\"", b"cau"), Token::EOF]
     );
 
     should!(
         string_single_escape,
         "\"c\\'au\"",
-        vec![Token::StringLiteral(String::from("c'au")), Token::EOF]
+        vec![string("\"c\\'au\"", b"c'au"), Token::EOF]
     );
 
     should!(
         string_single_unescape,
         "'\t'",
-        vec![Token::NumericLiteral(Number::new(b'\t' as u32, 0, 0, 10)), Token::EOF]
+        vec![
+            chr("'\t'", b'\t'),
+            Token::EOF
+        ]
     );
 
     should!(
         string_double,
         r#""cau""#,
-        vec![Token::StringLiteral(String::from("cau")), Token::EOF]
+        vec![string(r#""cau""#, b"cau"), Token::EOF]
     );
 
     should!(
         string_double_escape,
         r#""c\"au""#,
-        vec![Token::StringLiteral(String::from("c\"au")), Token::EOF]
+        vec![string(r#""c\"au""#, b"c\"au"), Token::EOF]
     );
 
     should!(
         string_double_unescape,
         "\"\t\"",
-        vec![Token::StringLiteral(String::from("\t")), Token::EOF]
+        vec![string("\"\t\"", b"\t"), Token::EOF]
     );
 
     should!(
         string_unicode,
+        "\"\\u00A0\\u0024\"",
+        vec![string("\"\\u00A0\\u0024\"", "\u{A0}$".as_bytes()), Token::EOF]
+    );
+
+    should_fail!(
+        string_unicode_basic,
         "\"\\u004E\"",
-        vec![Token::StringLiteral(String::from("N")), Token::EOF]
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\u004E")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should!(
+        string_unicode_basic_c23,
+        "\"\\u0041\"",
+        LexerOptions::for_standard(Standard::C23),
+        vec![string("\"\\u0041\"", b"A"), Token::EOF]
     );
 
     should!(
         string_hex,
         "\"\\x4E\"",
-        vec![Token::StringLiteral(String::from("N")), Token::EOF]
+        vec![string("\"\\x4E\"", b"N"), Token::EOF]
+    );
+
+    should_fail!(
+        string_unterminated,
+        "x = \"abc\n\";",
+        Error::new(ErrorKind::UnterminatedString, Position::new(4, 1, 5))
+    );
+
+    should_fail!(
+        string_unterminated_eof,
+        "\"abc\\\"",
+        Error::new(ErrorKind::UnterminatedString, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        string_unicode_short,
+        "\"ab\\u12\"",
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\u12")),
+            Position::new(3, 1, 4)
+        )
+    );
+
+    should_fail!(
+        string_unicode_surrogate,
+        "\"\\uD800\"",
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\uD800")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should_fail!(
+        char_empty,
+        "''",
+        Error::new(ErrorKind::EmptyCharConstant, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        char_unterminated_eof,
+        "'",
+        Error::new(ErrorKind::UnterminatedChar, Position::new(0, 1, 1))
+    );
+
+    should!(
+        string_utf8,
+        "\"é\\t€\"",
+        vec![string("\"é\\t€\"", "é\t€".as_bytes()), Token::EOF]
+    );
+
+    should!(
+        string_simple_escapes,
+        r#""\a\b\f\n\r\t\v\'\"\?\\""#,
+        vec![
+            string(
+                r#""\a\b\f\n\r\t\v\'\"\?\\""#,
+                b"\x07\x08\x0C\n\r\t\x0B'\"?\\"
+            ),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        string_octal,
+        r#""\0\12\3770\1a""#,
+        vec![string(r#""\0\12\3770\1a""#, b"\0\n\xFF0\x01a"), Token::EOF]
+    );
+
+    should!(
+        string_hex_any_length,
+        r#""\x41\x0000042\xfg""#,
+        vec![string(r#""\x41\x0000042\xfg""#, b"AB\x0Fg"), Token::EOF]
+    );
+
+    should!(
+        string_universal_long,
+        r#""\U0001F600\u00e9""#,
+        vec![string(r#""\U0001F600\u00e9""#, "😀é".as_bytes()), Token::EOF]
+    );
+
+    should_fail!(
+        string_hex_out_of_range,
+        r#"s = "ab\x100";"#,
+        Error::new(
+            ErrorKind::EscapeOutOfRange(String::from("\\x100")),
+            Position::new(7, 1, 8)
+        )
+    );
+
+    should_fail!(
+        string_octal_out_of_range,
+        r#""\400""#,
+        Error::new(
+            ErrorKind::EscapeOutOfRange(String::from("\\400")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should_fail!(
+        string_hex_no_digits,
+        r#""\xg""#,
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\x")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should_fail!(
+        string_unknown_escape,
+        r#""a\qb""#,
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\q")),
+            Position::new(2, 1, 3)
+        )
+    );
+
+    should_fail!(
+        string_universal_out_of_range,
+        r#""\U00110000""#,
+        Error::new(
+            ErrorKind::InvalidEscape(String::from("\\U00110000")),
+            Position::new(1, 1, 2)
+        )
+    );
+
+    should!(
+        char_escapes,
+        r"'\377' '\x41' '\a' '\''",
+        vec![
+            chr(r"'\377'", 255),
+            chr(r"'\x41'", 65),
+            chr(r"'\a'", 7),
+            chr(r"'\''", 39),
+            Token::EOF
+        ]
+    );
+
+    #[test]
+    fn string_text() {
+        let tokens = Lexer::lex(r#""é\x41" "\xff" u"\xD800" U"é😀""#).unwrap();
+        let text: Vec<_> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::StringLiteral(s) => Some(s.text()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            text,
+            vec![Some("éA".to_string()), None, None, Some("é😀".to_string())]
+        );
+    }
+
+    fn prefixed(raw: &str, encoding: Encoding, value: CodeUnits) -> Token {
+        Token::StringLiteral(StringLiteral::new(raw.to_string(), encoding, value))
+    }
+
+    should!(
+        string_prefixes,
+        r#"u8"é" u"😀" U"😀" L"é""#,
+        vec![
+            prefixed(r#"u8"é""#, Encoding::Utf8, CodeUnits::U8(vec![0xC3, 0xA9])),
+            prefixed(r#"u"😀""#, Encoding::Utf16, CodeUnits::U16(vec![0xD83D, 0xDE00])),
+            prefixed(r#"U"😀""#, Encoding::Utf32, CodeUnits::U32(vec![0x1F600])),
+            prefixed(r#"L"é""#, Encoding::Wide, CodeUnits::U32(vec![0xE9])),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        string_wide_short,
+        r#"L"\xFFFF😀""#,
+        LexerOptions {
+            short_wchar: true,
+            ..LexerOptions::default()
+        },
+        vec![
+            prefixed(
+                r#"L"\xFFFF😀""#,
+                Encoding::Wide,
+                CodeUnits::U16(vec![0xFFFF, 0xD83D, 0xDE00])
+            ),
+            Token::EOF
+        ]
     );
 
-}
\ No newline at end of file
+    should_fail!(
+        string_prefixed_out_of_range,
+        r#"u"\x10000""#,
+        Error::new(
+            ErrorKind::EscapeOutOfRange(String::from("\\x10000")),
+            Position::new(2, 1, 3)
+        )
+    );
+
+    should_fail!(
+        string_prefixed_unterminated,
+        "x = L\"abc",
+        Error::new(ErrorKind::UnterminatedString, Position::new(4, 1, 5))
+    );
+
+    should!(
+        char_prefixes,
+        r"L'é' u'\xFFFF' U'\U0001F600'",
+        vec![
+            Token::CharLiteral(CharLiteral::new(
+                String::from("L'é'"),
+                Encoding::Wide,
+                CodeUnits::U32(vec![0xE9]),
+                0xE9
+            )),
+            Token::CharLiteral(CharLiteral::new(
+                String::from(r"u'\xFFFF'"),
+                Encoding::Utf16,
+                CodeUnits::U16(vec![0xFFFF]),
+                0xFFFF
+            )),
+            Token::CharLiteral(CharLiteral::new(
+                String::from(r"U'\U0001F600'"),
+                Encoding::Utf32,
+                CodeUnits::U32(vec![0x1F600]),
+                0x1F600
+            )),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        prefix_like_identifiers,
+        r#"u8 'a' Lx"b" u8'c'"#,
+        vec![
+            Token::Identifier(IStr::new("u8")),
+            chr("'a'", b'a'),
+            Token::Identifier(IStr::new("Lx")),
+            string(r#""b""#, b"b"),
+            Token::Identifier(IStr::new("u8")),
+            chr("'c'", b'c'),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        prefixes_before_c11,
+        r#"u"a" L'b'"#,
+        LexerOptions::for_standard(Standard::C99),
+        vec![
+            Token::Identifier(IStr::new("u")),
+            string(r#""a""#, b"a"),
+            Token::CharLiteral(CharLiteral::new(
+                String::from("L'b'"),
+                Encoding::Wide,
+                CodeUnits::U32(vec![98]),
+                98
+            )),
+            Token::EOF
+        ]
+    );
+
+    should!(
+        utf8_char_c23,
+        r"u8'c' u8'\xFF'",
+        LexerOptions::for_standard(Standard::C23),
+        vec![
+            Token::CharLiteral(CharLiteral::new(
+                String::from("u8'c'"),
+                Encoding::Utf8,
+                CodeUnits::U8(vec![b'c']),
+                99
+            )),
+            Token::CharLiteral(CharLiteral::new(
+                String::from(r"u8'\xFF'"),
+                Encoding::Utf8,
+                CodeUnits::U8(vec![0xFF]),
+                0xFF
+            )),
+            Token::EOF
+        ]
+    );
+
+    should_fail!(
+        utf8_char_too_long,
+        "u8'é'",
+        LexerOptions::for_standard(Standard::C23),
+        Error::new(ErrorKind::CharConstantTooLong, Position::new(0, 1, 1))
+    );
+
+    fn char_value(input: &str, options: LexerOptions) -> (i64, Vec<ErrorKind>) {
+        let mut lexer = Lexer::with_options(input, options);
+        match lexer.next() {
+            Some(Ok(Token::CharLiteral(c))) => {
+                let warnings = lexer.warnings().iter().map(|w| w.kind.clone()).collect();
+                (c.value, warnings)
+            }
+            t => panic!("{:?} is not a character constant", t),
+        }
+    }
+
+    #[test]
+    fn char_values() {
+        let default = LexerOptions::default();
+        let unsigned = LexerOptions {
+            signed_char: false,
+            ..LexerOptions::default()
+        };
+        let short_wchar = LexerOptions {
+            short_wchar: true,
+            ..LexerOptions::default()
+        };
+        assert_eq!(char_value("'a'", default), (97, vec![]));
+        assert_eq!(char_value(r"'\377'", default), (-1, vec![]));
+        assert_eq!(char_value(r"'\377'", unsigned), (255, vec![]));
+        assert_eq!(char_value(r"'\0'", default), (0, vec![]));
+        assert_eq!(char_value(r"u'\xFFFF'", default), (0xFFFF, vec![]));
+        assert_eq!(char_value("U'😀'", default), (0x1F600, vec![]));
+        assert_eq!(char_value(r"L'\xFFFFFFFF'", default), (-1, vec![]));
+        assert_eq!(char_value(r"L'\xFFFF'", short_wchar), (0xFFFF, vec![]));
+    }
+
+    #[test]
+    fn char_multi() {
+        let multi = vec![ErrorKind::MultiCharConstant];
+        let long = vec![ErrorKind::CharConstantTooLong];
+        let default = LexerOptions::default();
+        assert_eq!(char_value("'ab'", default), (0x6162, multi.clone()));
+        assert_eq!(char_value("'é'", default), (0xC3A9, multi.clone()));
+        assert_eq!(char_value(r"'\377\377\377\377'", default), (-1, multi));
+        assert_eq!(char_value("'abcde'", default), (0x6263_6465, long.clone()));
+        assert_eq!(char_value("L'ab'", default), (98, long));
+    }
+
+    #[test]
+    fn char_warning_position() {
+        let mut lexer = Lexer::new("x = 'ab';");
+        assert_eq!(lexer.by_ref().count(), 4);
+        assert_eq!(
+            lexer.warnings(),
+            &[Warning::new(ErrorKind::MultiCharConstant, Position::new(4, 1, 5))]
+        );
+        assert_eq!(
+            lexer.warnings()[0].to_string(),
+            "1:5: warning: multi-character character constant"
+        );
+    }
+
+    should_fail!(
+        char_multi_rejected,
+        "x = 'ab';",
+        LexerOptions {
+            multichar: MultiChar::Reject,
+            ..LexerOptions::default()
+        },
+        Error::new(ErrorKind::MultiCharConstant, Position::new(4, 1, 5))
+    );
+
+    should_fail!(
+        char_utf16_surrogates,
+        "u'😀'",
+        Error::new(ErrorKind::CharConstantTooLong, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        char_utf32_two,
+        "U'ab'",
+        Error::new(ErrorKind::CharConstantTooLong, Position::new(0, 1, 1))
+    );
+
+    should_fail!(
+        char_prefixed_empty,
+        "L''",
+        Error::new(ErrorKind::EmptyCharConstant, Position::new(0, 1, 1))
+    );
+}
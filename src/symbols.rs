@@ -0,0 +1,163 @@
+//! Scoped symbol table resolving the typedef-name ambiguity of C.
+//!
+//! Whether `T * x;` declares a pointer or multiplies depends on whether
+//! `T` is a typedef name, which only the parser knows. The parser
+//! declares names in a [`SymbolTable`] as it goes and the lexer looks
+//! identifiers up in it, producing [`Token::TypedefName`] and
+//! [`Token::EnumerationConstant`] instead of [`Token::Identifier`].
+//!
+//! [`Token::TypedefName`]: crate::token::Token::TypedefName
+//! [`Token::EnumerationConstant`]: crate::token::Token::EnumerationConstant
+//! [`Token::Identifier`]: crate::token::Token::Identifier
+
+use std::collections::HashMap;
+
+/// What an ordinary identifier is declared as
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Symbol {
+    /// Name declared by `typedef`
+    Typedef,
+    /// Enumeration constant
+    EnumConstant,
+    /// Object, function or parameter, it hides typedef names and
+    /// enumeration constants of enclosing scopes
+    Ordinary,
+}
+
+/// Ordinary identifiers declared in nested scopes
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct SymbolTable {
+    // innermost scope is the last one, file scope is never popped
+    scopes: Vec<HashMap<String, Symbol>>,
+}
+
+impl Default for SymbolTable {
+    fn default() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+        }
+    }
+}
+
+impl SymbolTable {
+    /// Create symbol table with empty file scope
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Enter block or function prototype scope
+    #[inline]
+    pub fn push_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    /// Leave innermost scope forgetting names declared in it, file scope
+    /// is kept
+    #[inline]
+    pub fn pop_scope(&mut self) {
+        if self.scopes.len() > 1 {
+            self.scopes.pop();
+        }
+    }
+
+    /// Number of scopes, `1` at file scope
+    #[inline]
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    /// Declare typedef name in innermost scope
+    #[inline]
+    pub fn declare_typedef(&mut self, name: &str) {
+        self.declare(name, Symbol::Typedef);
+    }
+
+    /// Declare enumeration constant in innermost scope
+    #[inline]
+    pub fn declare_enum_constant(&mut self, name: &str) {
+        self.declare(name, Symbol::EnumConstant);
+    }
+
+    /// Declare object, function or parameter in innermost scope
+    #[inline]
+    pub fn declare_ordinary(&mut self, name: &str) {
+        self.declare(name, Symbol::Ordinary);
+    }
+
+    #[inline]
+    fn declare(&mut self, name: &str, symbol: Symbol) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), symbol);
+        }
+    }
+
+    /// Innermost declaration of name, `None` if it isn't declared
+    #[inline]
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{token::Token, Lexer};
+    use internship::IStr;
+
+    #[test]
+    fn scopes() {
+        let mut symbols = SymbolTable::new();
+        symbols.declare_typedef("T");
+        symbols.push_scope();
+        assert_eq!(symbols.lookup("T"), Some(Symbol::Typedef));
+        symbols.declare_ordinary("T");
+        symbols.declare_enum_constant("E");
+        assert_eq!(symbols.lookup("T"), Some(Symbol::Ordinary));
+        assert_eq!(symbols.depth(), 2);
+        symbols.pop_scope();
+        symbols.pop_scope();
+        assert_eq!(symbols.depth(), 1);
+        assert_eq!(symbols.lookup("T"), Some(Symbol::Typedef));
+        assert_eq!(symbols.lookup("E"), None);
+    }
+
+    #[test]
+    fn classification() {
+        let mut lexer = Lexer::new("T * x; { T = RED; }");
+        lexer.symbols_mut().declare_typedef("T");
+        lexer.symbols_mut().declare_enum_constant("RED");
+        let mut tokens = Vec::new();
+        while let Some(token) = lexer.next() {
+            let token = token.unwrap();
+            // the parser tracks scopes and declarations
+            match &token {
+                Token::LBrace => {
+                    lexer.symbols_mut().push_scope();
+                    lexer.symbols_mut().declare_ordinary("T");
+                }
+                Token::RBrace => lexer.symbols_mut().pop_scope(),
+                _ => {}
+            }
+            tokens.push(token);
+        }
+        assert_eq!(
+            tokens,
+            vec![
+                Token::TypedefName(IStr::new("T")),
+                Token::Multi,
+                Token::Identifier(IStr::new("x")),
+                Token::Semicolon,
+                Token::LBrace,
+                Token::Identifier(IStr::new("T")),
+                Token::Assign,
+                Token::EnumerationConstant(IStr::new("RED")),
+                Token::Semicolon,
+                Token::RBrace,
+            ]
+        );
+    }
+}
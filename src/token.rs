@@ -1,28 +1,377 @@
+use crate::span::Span;
 use internship::IStr;
 
 /// Number representation of parsed number
-#[derive(PartialEq, Debug, Clone, Copy)]
-pub struct Number {
-    /// Whole part of number
-    pub integer: u32,
-    /// Decimal part of number
-    pub decimal: u32,
-    /// Number behind E / e (exponent)
-    pub exponent: i64,
+#[derive(PartialEq, Debug, Clone)]
+pub enum Number {
+    /// Integer constant
+    Integer(Integer),
+    /// Floating constant
+    Float(Float),
+}
+
+/// Integer constant together with its suffix
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Integer {
+    /// Value of the constant
+    pub value: u128,
     /// base of number
     pub radix: u8,
+    /// `u` / `l` / `ll` / `wb` suffix of the constant
+    pub suffix: IntegerSuffix,
 }
 
-impl Number {
-    /// Create instance of js representation of number
+impl Integer {
+    /// Create new integer constant
     #[inline]
-    pub fn new(integer: u32, decimal: u32, exponent: i64, radix: u8) -> Self {
+    pub fn new(value: u128, radix: u8, suffix: IntegerSuffix) -> Self {
         Self {
-            integer,
-            decimal,
-            exponent,
+            value,
             radix,
+            suffix,
+        }
+    }
+
+    /// Type of the constant as chosen by C11 6.4.4.1: the first type
+    /// from the list allowed by suffix and radix which can represent the
+    /// value. `None` if the value doesn't fit any of them. Bit-precise
+    /// constants are `_BitInt(N)` of the smallest width `N` that can
+    /// represent the value.
+    pub fn ty(&self, model: &DataModel) -> Option<IntegerType> {
+        use self::IntegerType::*;
+
+        if self.suffix.length == IntegerLength::BitPrecise {
+            let bits = 128 - self.value.leading_zeros();
+            return Some(if self.suffix.unsigned {
+                UnsignedBitInt(bits.max(1))
+            } else {
+                BitInt(bits.max(1) + 1)
+            });
         }
+        let decimal = self.radix == 10;
+        let candidates: &[IntegerType] = match (self.suffix.unsigned, self.suffix.length, decimal) {
+            (false, IntegerLength::Plain, true) => &[Int, Long, LongLong],
+            (false, IntegerLength::Plain, false) => &[
+                Int,
+                UnsignedInt,
+                Long,
+                UnsignedLong,
+                LongLong,
+                UnsignedLongLong,
+            ],
+            (true, IntegerLength::Plain, _) => &[UnsignedInt, UnsignedLong, UnsignedLongLong],
+            (false, IntegerLength::Long, true) => &[Long, LongLong],
+            (false, IntegerLength::Long, false) => &[Long, UnsignedLong, LongLong, UnsignedLongLong],
+            (true, IntegerLength::Long, _) => &[UnsignedLong, UnsignedLongLong],
+            (false, IntegerLength::LongLong, true) => &[LongLong],
+            (false, IntegerLength::LongLong, false) => &[LongLong, UnsignedLongLong],
+            (true, IntegerLength::LongLong, _) => &[UnsignedLongLong],
+            (_, IntegerLength::BitPrecise, _) => &[],
+        };
+        candidates
+            .iter()
+            .cloned()
+            .find(|ty| self.value <= ty.max_value(model))
+    }
+}
+
+/// Suffix of integer constant
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub struct IntegerSuffix {
+    /// `u` or `U` is present
+    pub unsigned: bool,
+    /// `l`, `ll` or `wb` is present
+    pub length: IntegerLength,
+}
+
+impl IntegerSuffix {
+    /// Create new integer suffix
+    #[inline]
+    pub fn new(unsigned: bool, length: IntegerLength) -> Self {
+        Self { unsigned, length }
+    }
+}
+
+/// Length part of integer suffix
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum IntegerLength {
+    /// No length suffix
+    #[default]
+    Plain,
+    /// `l` or `L`
+    Long,
+    /// `ll` or `LL`, or MSVC `i64`
+    LongLong,
+    /// `wb` or `WB`, bit-precise `_BitInt(N)` since C23
+    BitPrecise,
+}
+
+/// Type of integer constant
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum IntegerType {
+    /// `int`, 32 bits under [`DataModel::LP64`]
+    Int,
+    /// `unsigned int`, 32 bits under [`DataModel::LP64`]
+    UnsignedInt,
+    /// `long`, 64 bits under [`DataModel::LP64`]
+    Long,
+    /// `unsigned long`, 64 bits under [`DataModel::LP64`]
+    UnsignedLong,
+    /// `long long`, 64 bits under [`DataModel::LP64`]
+    LongLong,
+    /// `unsigned long long`, 64 bits under [`DataModel::LP64`]
+    UnsignedLongLong,
+    /// `_BitInt(N)`
+    BitInt(u32),
+    /// `unsigned _BitInt(N)`
+    UnsignedBitInt(u32),
+}
+
+impl IntegerType {
+    /// Largest value representable by the type
+    pub fn max_value(self, model: &DataModel) -> u128 {
+        let (bits, signed) = match self {
+            IntegerType::Int => (model.int, true),
+            IntegerType::UnsignedInt => (model.int, false),
+            IntegerType::Long => (model.long, true),
+            IntegerType::UnsignedLong => (model.long, false),
+            IntegerType::LongLong => (model.long_long, true),
+            IntegerType::UnsignedLongLong => (model.long_long, false),
+            IntegerType::BitInt(bits) => (bits, true),
+            IntegerType::UnsignedBitInt(bits) => (bits, false),
+        };
+        let bits = if signed { bits.saturating_sub(1) } else { bits };
+        u128::MAX.checked_shr(128 - bits.min(128)).unwrap_or(0)
+    }
+}
+
+/// Widths in bits of integer types on the target
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct DataModel {
+    /// Width of `int`
+    pub int: u32,
+    /// Width of `long`
+    pub long: u32,
+    /// Width of `long long`
+    pub long_long: u32,
+}
+
+impl DataModel {
+    /// 64 bit Unix-like targets
+    pub const LP64: DataModel = DataModel {
+        int: 32,
+        long: 64,
+        long_long: 64,
+    };
+    /// 64 bit Windows
+    pub const LLP64: DataModel = DataModel {
+        int: 32,
+        long: 32,
+        long_long: 64,
+    };
+    /// 32 bit targets
+    pub const ILP32: DataModel = DataModel {
+        int: 32,
+        long: 32,
+        long_long: 64,
+    };
+}
+
+impl Default for DataModel {
+    fn default() -> Self {
+        DataModel::LP64
+    }
+}
+
+/// Floating constant
+#[derive(PartialEq, Debug, Clone)]
+pub struct Float {
+    /// Literal exactly as written in the source, including suffix
+    pub raw: String,
+    /// Value of the literal rounded to the nearest `f64`, constants with
+    /// `f` suffix have to be further rounded to `f32`
+    pub value: f64,
+    /// `f` / `l` suffix of the constant
+    pub suffix: FloatSuffix,
+}
+
+impl Float {
+    /// Create new floating constant
+    #[inline]
+    pub fn new(raw: String, value: f64, suffix: FloatSuffix) -> Self {
+        Self { raw, value, suffix }
+    }
+}
+
+/// Suffix of floating constant, which selects its type
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum FloatSuffix {
+    /// No suffix, `double`
+    #[default]
+    Plain,
+    /// `f` or `F`, `float`
+    Float,
+    /// `l` or `L`, `long double`
+    Long,
+}
+
+/// Encoding prefix of string literal or character constant
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum Encoding {
+    /// No prefix, `char`
+    #[default]
+    Plain,
+    /// `u8`, UTF-8 encoded `char`
+    Utf8,
+    /// `u`, UTF-16 encoded `char16_t`
+    Utf16,
+    /// `U`, UTF-32 encoded `char32_t`
+    Utf32,
+    /// `L`, `wchar_t`
+    Wide,
+}
+
+impl Encoding {
+    /// Prefix as written in the source
+    #[inline]
+    pub fn prefix(self) -> &'static str {
+        match self {
+            Encoding::Plain => "",
+            Encoding::Utf8 => "u8",
+            Encoding::Utf16 => "u",
+            Encoding::Utf32 => "U",
+            Encoding::Wide => "L",
+        }
+    }
+}
+
+/// Decoded contents of literal as a sequence of code units of its
+/// encoding
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub enum CodeUnits {
+    /// `char` units, characters are encoded in UTF-8
+    U8(Vec<u8>),
+    /// `char16_t` units, or `wchar_t` when it is 16 bits wide
+    U16(Vec<u16>),
+    /// `char32_t` units, or `wchar_t` when it is 32 bits wide
+    U32(Vec<u32>),
+}
+
+impl CodeUnits {
+    /// Number of code units
+    #[inline]
+    pub fn len(&self) -> usize {
+        match self {
+            CodeUnits::U8(units) => units.len(),
+            CodeUnits::U16(units) => units.len(),
+            CodeUnits::U32(units) => units.len(),
+        }
+    }
+
+    /// Whether there are no code units
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Code unit at `index` widened to `u32`
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<u32> {
+        match self {
+            CodeUnits::U8(units) => units.get(index).map(|u| u32::from(*u)),
+            CodeUnits::U16(units) => units.get(index).map(|u| u32::from(*u)),
+            CodeUnits::U32(units) => units.get(index).copied(),
+        }
+    }
+
+    /// Contents as text if the code units are valid in their encoding
+    #[inline]
+    pub fn text(&self) -> Option<String> {
+        match self {
+            CodeUnits::U8(units) => String::from_utf8(units.clone()).ok(),
+            CodeUnits::U16(units) => String::from_utf16(units).ok(),
+            CodeUnits::U32(units) => units.iter().map(|u| std::char::from_u32(*u)).collect(),
+        }
+    }
+}
+
+/// String literal
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct StringLiteral {
+    /// Literal exactly as written in the source, including prefix and
+    /// quotes
+    pub raw: String,
+    /// Encoding prefix of the literal
+    pub encoding: Encoding,
+    /// Contents with escape sequences decoded, octal and hexadecimal
+    /// escapes give single code units so the value is not necessarily
+    /// valid in its encoding
+    pub value: CodeUnits,
+}
+
+impl StringLiteral {
+    /// Create new string literal
+    #[inline]
+    pub fn new(raw: String, encoding: Encoding, value: CodeUnits) -> Self {
+        Self {
+            raw,
+            encoding,
+            value,
+        }
+    }
+
+    /// Decoded contents as text if they are valid in the encoding of
+    /// the literal
+    #[inline]
+    pub fn text(&self) -> Option<String> {
+        self.value.text()
+    }
+}
+
+/// Character constant
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct CharLiteral {
+    /// Constant exactly as written in the source, including prefix and
+    /// quotes
+    pub raw: String,
+    /// Encoding prefix of the constant
+    pub encoding: Encoding,
+    /// Contents with escape sequences decoded
+    pub units: CodeUnits,
+    /// Value of the constant converted to its type: `int` for plain
+    /// constants, `unsigned char`, `char16_t` and `char32_t` for UTF
+    /// ones, `wchar_t` for wide ones
+    pub value: i64,
+}
+
+impl CharLiteral {
+    /// Create new character constant
+    #[inline]
+    pub fn new(raw: String, encoding: Encoding, units: CodeUnits, value: i64) -> Self {
+        Self {
+            raw,
+            encoding,
+            units,
+            value,
+        }
+    }
+}
+
+/// Header name of `#include` directive
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct HeaderName {
+    /// Name between the delimiters, taken as is without escapes
+    pub name: String,
+    /// Name is written in angle brackets like `<stdio.h>` rather than
+    /// in quotes like `"config.h"`
+    pub system: bool,
+}
+
+impl HeaderName {
+    /// Create new header name
+    #[inline]
+    pub fn new(name: String, system: bool) -> Self {
+        Self { name, system }
     }
 }
 
@@ -54,7 +403,9 @@ pub enum Token {
     Mod,          // %
     Identifier(IStr),
     NumericLiteral(Number),
-    StringLiteral(String),
+    StringLiteral(StringLiteral),
+    CharLiteral(CharLiteral),
+    HeaderName(HeaderName),
     FuncName,    // __func__
     SIZEOF,      // sizeof
     PtrOp,       // ->
@@ -78,11 +429,14 @@ pub enum Token {
     AndAssign,   // &=
     XorAssign,   // ^=
     OrAssign,    // |=
-    // TODO: this should be done when we found this is a typedef name,
-    //       typedef LL int, then LL is typedef_name
-    TypedefName,
-    ELLIPSIS,                    // ...
-    EnumerationConstant(String), // TODO: add check
+    Hash,        // # %:
+    HashHash,    // ## %:%:
+    ColonColon,  // ::
+    // identifiers declared in the symbol table of the lexer, see
+    // `symbols`
+    TypedefName(IStr),
+    ELLIPSIS, // ...
+    EnumerationConstant(IStr),
     LineTerminator,
     EOF,
 
@@ -129,6 +483,69 @@ pub enum Token {
     NORETURN,
     StaticAssert,
     ThreadLocal,
+    TRUE,
+    FALSE,
+    NULLPTR,
+    CONSTEXPR,
+    TYPEOF,
+    TypeofUnqual,
+    BitInt,
+    DECIMAL32,
+    DECIMAL64,
+    DECIMAL128,
+    // GNU extensions
+    ATTRIBUTE,
+    ASM,
+    EXTENSION,
+    BuiltinVaArg,
+    LABEL,
+    INT128,
+    FLOAT128,
+    AutoType,
+    // MSVC extensions
+    DECLSPEC,
+    CDECL,
+    STDCALL,
+    FASTCALL,
+    INT8,
+    INT16,
+    INT32,
+    INT64,
+    FORCEINLINE,
+    PTR32,
+    PTR64,
+    TRY,
+    EXCEPT,
+    FINALLY,
+}
+
+/// Token together with the part of the source it was lexed from
+#[derive(PartialEq, Clone, Debug)]
+pub struct SpannedToken {
+    /// Lexed token
+    pub token: Token,
+    /// Location of the token in the source
+    pub span: Span,
+    /// Token is the first one on its line, only white space and comments
+    /// are before it. Preprocessing directives start with such `#`.
+    pub line_start: bool,
+    /// File name given by the last `#line` directive or linemarker
+    /// before the token when they are handled, see
+    /// [`LexerOptions::line_markers`](crate::options::LexerOptions::line_markers)
+    pub file: Option<IStr>,
+}
+
+impl SpannedToken {
+    /// Create new spanned token
+    #[inline]
+    pub fn new(token: Token, span: Span, line_start: bool) -> Self {
+        Self {
+            token,
+            span,
+            line_start,
+            file: None,
+        }
+    }
 }
 
 pub const TOKENS: phf::Map<&'static str, Token> = phf_map! {
@@ -175,4 +592,13 @@ pub const TOKENS: phf::Map<&'static str, Token> = phf_map! {
     "&=" => Token::AndAssign,
     "^=" => Token::XorAssign,
     "|=" => Token::OrAssign,
+    "#" => Token::Hash,
+    "##" => Token::HashHash,
+    "::" => Token::ColonColon,
+    "<:" => Token::LBracket,
+    ":>" => Token::RBracket,
+    "<%" => Token::LBrace,
+    "%>" => Token::RBrace,
+    "%:" => Token::Hash,
+    "%:%:" => Token::HashHash,
 };
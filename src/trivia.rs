@@ -0,0 +1,168 @@
+//! Lossless lexing keeping white space and comments.
+//!
+//! Besides tokens, white space and comments between them are returned
+//! with their exact text, so concatenating the texts of all tokens gives
+//! back the input byte for byte. Line terminators are tokens as usual
+//! and line comments end before the newline after them.
+
+use crate::{
+    error::Error,
+    options::LexerOptions,
+    span::{Position, Span},
+    state_machine::Tokenizer,
+    token::Token,
+};
+
+/// Part of the source between tokens
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Trivia {
+    /// Spaces, tabs, vertical tabs, form feeds and line splices
+    Whitespace,
+    /// `//` comment without the newline after it
+    LineComment,
+    /// `/* */` comment
+    BlockComment,
+}
+
+/// Either a token or trivia
+#[derive(PartialEq, Debug, Clone)]
+pub enum TokenOrTrivia {
+    /// Token, the same as lexed by [`Lexer`](crate::Lexer)
+    Token(Token),
+    /// White space or comment
+    Trivia(Trivia),
+}
+
+/// Token of lossless token stream
+#[derive(PartialEq, Debug, Clone)]
+pub struct LosslessToken {
+    /// Token or trivia
+    pub kind: TokenOrTrivia,
+    /// Exact text in the source
+    pub text: String,
+    /// Location in the source
+    pub span: Span,
+}
+
+impl LosslessToken {
+    /// Create new lossless token
+    #[inline]
+    pub fn new(kind: TokenOrTrivia, text: String, span: Span) -> Self {
+        Self { kind, text, span }
+    }
+}
+
+struct Lossless<'a> {
+    source: &'a [u8],
+    tokens: Vec<LosslessToken>,
+    // end of the last pushed token
+    end: Position,
+}
+
+impl<'a> Lossless<'a> {
+    #[inline]
+    fn push(&mut self, kind: TokenOrTrivia, span: Span) {
+        // the source between tokens is white space
+        if self.end.offset < span.start.offset {
+            let whitespace = Span::new(self.end, span.start);
+            self.push_text(TokenOrTrivia::Trivia(Trivia::Whitespace), whitespace);
+        }
+        self.push_text(kind, span);
+    }
+
+    #[inline]
+    fn push_text(&mut self, kind: TokenOrTrivia, span: Span) {
+        let text = &self.source[span.start.offset..span.end.offset];
+        let text = String::from_utf8_lossy(text).into_owned();
+        self.tokens.push(LosslessToken::new(kind, text, span));
+        self.end = span.end;
+    }
+}
+
+/// Lex input into tokens and trivia between them, ends with
+/// [`Token::EOF`]. `#line` directives are lexed as other tokens.
+pub fn lex_lossless(input: &str, options: LexerOptions) -> Result<Vec<LosslessToken>, Error> {
+    let mut tokenizer = Tokenizer::lossless(input, options);
+    let mut lossless = Lossless {
+        source: input.as_bytes(),
+        tokens: Vec::new(),
+        end: Position::default(),
+    };
+    loop {
+        let token = tokenizer.next_token()?;
+        for (trivia, span) in tokenizer.take_comments() {
+            lossless.push(TokenOrTrivia::Trivia(trivia), span);
+        }
+        match token {
+            Some(token) => lossless.push(TokenOrTrivia::Token(token.token), token.span),
+            None => break,
+        }
+    }
+    let mut end = lossless.end;
+    end.advance(input.as_bytes(), input.len());
+    lossless.push(TokenOrTrivia::Token(Token::EOF), Span::new(end, end));
+    Ok(lossless.tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use internship::IStr;
+
+    fn lossless(input: &str) -> Vec<LosslessToken> {
+        lex_lossless(input, LexerOptions::default()).unwrap()
+    }
+
+    #[test]
+    fn trivia() {
+        let tokens: Vec<(TokenOrTrivia, String)> = lossless("a /* b\n */\t// c\r\n")
+            .into_iter()
+            .map(|t| (t.kind, t.text))
+            .collect();
+        let trivia = |trivia, text: &str| (TokenOrTrivia::Trivia(trivia), text.to_string());
+        let token = |token, text: &str| (TokenOrTrivia::Token(token), text.to_string());
+        assert_eq!(
+            tokens,
+            vec![
+                token(Token::Identifier(IStr::new("a")), "a"),
+                trivia(Trivia::Whitespace, " "),
+                trivia(Trivia::BlockComment, "/* b\n */"),
+                trivia(Trivia::Whitespace, "\t"),
+                trivia(Trivia::LineComment, "// c"),
+                token(Token::LineTerminator, "\r"),
+                token(Token::LineTerminator, "\n"),
+                token(Token::EOF, ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        for input in &[
+            "",
+            "  \n",
+            "// comment at the end",
+            "int lo\\\nng = 1; \\\n /\\\n* spliced *\\\n/ x \\\n",
+            "#define X(a) #a /* */ // x\n# 1 \"a.c\"\n\x0c\x0b\t",
+            "s = \"a\\\\\" 'b' u8\"\\377\";",
+        ] {
+            let tokens = lossless(input);
+            let text: String = tokens.iter().map(|t| t.text.as_str()).collect();
+            assert_eq!(&text, input);
+            let mut offset = 0;
+            for t in &tokens {
+                assert_eq!(t.span.start.offset, offset, "input {:?}", input);
+                offset = t.span.end.offset;
+            }
+        }
+        let options = LexerOptions {
+            trigraphs: true,
+            ..Default::default()
+        };
+        let input = "??=define a ??/\nb??/\n/* c */";
+        let tokens = lex_lossless(input, options).unwrap();
+        let text: String = tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(text, input);
+        assert_eq!(tokens[0].kind, TokenOrTrivia::Token(Token::Hash));
+    }
+}
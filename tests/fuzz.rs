@@ -0,0 +1,171 @@
+//! Property tests checking that the lexer is total: every input either
+//! produces tokens or an error, never a panic. Inputs are generated by a
+//! seeded PRNG so runs are reproducible and don't need network access.
+//! Set `C_LEXER_FUZZ_ITERATIONS` to run longer.
+
+extern crate c_lexer;
+
+use c_lexer::{
+    options::{LexerOptions, Standard},
+    preprocess::Preprocessor,
+    token::Token,
+    Lexer,
+};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
+
+/// Characters that drive the state machine into interesting states
+const ALPHABET: &[&str] = &[
+    "a", "b", "e", "E", "f", "F", "l", "L", "u", "U", "x", "X", "o", "O", "p", "P", "_", "$", "0",
+    "1", "7", "8", "9", ".", "+", "-", "*", "/", "%", "=", "<", ">", "!", "&", "|", "^", "~", "?",
+    ":", ";", ",", "(", ")", "[", "]", "{", "}", "#", "@", "`", "'", "\"", "\\", " ", "\t", "\n",
+    "\r", "\u{0B}", "\u{0C}", "\u{A0}", "é", "€", "😀", "\0",
+];
+
+/// Valid C fragments, truncated and recombined by the tests
+const CORPUS: &[&str] = &[
+    "int main(void) { return 0; }\n",
+    "#include <stdio.h>\n  # include \"a.h\"\n#define CAT(a, b) a ## b #a\n",
+    "#define F(x, ...) f(#x, x ## 1, __VA_ARGS__)\n#if defined F && F(1, 2) > 0\nF(a, b)\n#else\nx\n#endif\n",
+    "a[i] = 15 - i; x <<= 2; y >>= 3; z = a->b + c++ - --d;\n",
+    "printf(\"%d \\n\\t\\\"quoted\\\"\", a[i]);\n",
+    "char c = '\\''; char d = '\\x4E'; char e = '\\u00E9';\n",
+    "wchar_t *w = L\"wide\"; char *s = u8\"\\377\"; int c = U'\\U0001F600' + u'x';\n",
+    "/* block\n comment */ // line comment\n",
+    "int lo\\\nng = 1\\\r\n0; s = \"spl\\\niced\"; // also \\\n spliced\n",
+    "double d = 10.5e-3 + 0x1f + 0b101 + 0o17 + 017 + .5 + 5.;\n",
+    "float f = 0x1.8p3f + 0X.8P-1 + 1e10L + 2.5F;\n",
+    "if (a != b && c || !d) { e ^= f; g |= h; i &= j; k %= l; }\n",
+    "x = a ? b : c; y = sizeof(int); z = ...; w = a...b;\n",
+    "# 1 \"a.c\"\n# 5 \"b.h\" 1 3 4\n#line 10\nint x;\n#line 2 \"c.c\"\n",
+    "int a<:2:> = <% 1, 2 %>; %:%: b ??( c ??) d ??/\ne;\n",
+    "[[gnu::unused]] constexpr bool b = true; auto x = 1'000'000uwb + 0x1'fp1'0 + u8'c';\n",
+    "__extension__ typedef unsigned __int128 u128 __attribute__((aligned(16)));\n__asm__ __volatile__(\"nop\");\n",
+    "__declspec(dllimport) unsigned __int64 __stdcall f(void) { __try { return 1ui64; } __finally {} }\n",
+];
+
+/// xorshift64* generator, good enough to produce varied inputs
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+fn iterations() -> usize {
+    env::var("C_LEXER_FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(20_000)
+}
+
+/// Lex input and check invariants of the result, the preprocessor has
+/// to be total too
+fn check(input: &str) {
+    let files: HashMap<String, String> = CORPUS
+        .iter()
+        .enumerate()
+        .map(|(i, source)| (format!("{}.h", i), source.to_string()))
+        .collect();
+    let _ = Preprocessor::new(files).preprocess("fuzz.c", input);
+    let defined: HashSet<String> = vec!["F".to_string()].into_iter().collect();
+    let _ = Lexer::lex_active(input, &defined);
+    let options = LexerOptions {
+        line_markers: true,
+        gnu: true,
+        ..Default::default()
+    };
+    let _: Vec<_> = Lexer::with_options(input, options).collect();
+    let c23 = LexerOptions {
+        msvc: true,
+        ..LexerOptions::for_standard(Standard::C23)
+    };
+    let _: Vec<_> = Lexer::with_options(input, c23).collect();
+    if let Ok(tokens) = Lexer::lex_lossless(input) {
+        let text: String = tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(text, input, "lossless tokens reproduce input");
+    }
+    match Lexer::lex_spanned(input) {
+        Ok(tokens) => {
+            let last = tokens.last().expect("token stream is never empty");
+            assert_eq!(last.token, Token::EOF, "input {:?}", input);
+            let mut offset = 0;
+            let mut line = 1;
+            for t in &tokens {
+                assert!(t.span.start.offset >= offset, "input {:?}", input);
+                assert!(t.span.start.offset <= t.span.end.offset, "input {:?}", input);
+                assert!(t.span.end.offset <= input.len(), "input {:?}", input);
+                assert!(t.span.start.line >= line, "input {:?}", input);
+                offset = t.span.end.offset;
+                line = t.span.end.line;
+            }
+            let plain = Lexer::lex(input).expect("lex agrees with lex_spanned");
+            assert_eq!(plain.len(), tokens.len(), "input {:?}", input);
+            let streamed: Vec<_> = Lexer::new(input).map(Result::unwrap).collect();
+            assert_eq!(streamed.len() + 1, tokens.len(), "input {:?}", input);
+        }
+        Err(e) => {
+            assert!(e.position.offset <= input.len(), "input {:?}", input);
+            assert!(e.position.line >= 1, "input {:?}", input);
+            assert!(e.position.column >= 1, "input {:?}", input);
+            assert!(Lexer::lex(input).is_err(), "input {:?}", input);
+            let streamed = Lexer::new(input).last();
+            assert_eq!(streamed, Some(Err(e)), "input {:?}", input);
+        }
+    }
+}
+
+#[test]
+fn random_alphabet() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..iterations() {
+        let len = rng.below(24);
+        let input: String = (0..len)
+            .map(|_| ALPHABET[rng.below(ALPHABET.len())])
+            .collect();
+        check(&input);
+    }
+}
+
+#[test]
+fn truncated_corpus() {
+    for source in CORPUS {
+        for (i, _) in source.char_indices() {
+            check(&source[..i]);
+            check(&source[i..]);
+        }
+    }
+}
+
+#[test]
+fn mutated_corpus() {
+    let mut rng = Rng(0xD1B5_4A32_D192_ED03);
+    for _ in 0..iterations() {
+        let source = CORPUS[rng.below(CORPUS.len())];
+        let mut input: Vec<&str> = source
+            .char_indices()
+            .map(|(i, c)| &source[i..i + c.len_utf8()])
+            .collect();
+        for _ in 0..=rng.below(3) {
+            let at = rng.below(input.len() + 1);
+            match rng.below(3) {
+                0 if at < input.len() => {
+                    input.remove(at);
+                }
+                1 if at < input.len() => input[at] = ALPHABET[rng.below(ALPHABET.len())],
+                _ => input.insert(at, ALPHABET[rng.below(ALPHABET.len())]),
+            }
+        }
+        check(&input.concat());
+    }
+}